@@ -0,0 +1,213 @@
+//! Tests that `test_stubs` rejects invalid input. These are `compile_fail` doctests since they
+//! must be compiled separately from each other.
+
+/// `test_stubs` can only be applied to traits and impl blocks, and methods without a body are only
+/// stubbed in trait implementations.
+///
+/// ```compile_fail
+/// # use test_stubs::test_stubs;
+/// #[test_stubs]
+/// struct S;
+/// ```
+///
+/// ```compile_fail
+/// # use test_stubs::test_stubs;
+/// struct S;
+/// #[test_stubs]
+/// impl S {
+///     fn f(&self);
+/// }
+/// ```
+struct NonTrait;
+
+/// Unknown options are rejected.
+///
+/// ```compile_fail
+/// # use test_stubs::test_stubs;
+/// #[test_stubs(unknown)]
+/// trait T {
+///     fn f(&self);
+/// }
+/// ```
+struct UnknownOption;
+
+/// Options which take a string only accept the documented values.
+///
+/// ```compile_fail
+/// # use test_stubs::test_stubs;
+/// #[test_stubs(result = "maybe")]
+/// trait T {
+///     fn f(&self) -> Result<u8, ()>;
+/// }
+/// ```
+///
+/// ```compile_fail
+/// # use test_stubs::test_stubs;
+/// #[test_stubs(collections = "default")]
+/// trait T {
+///     fn f(&self) -> Vec<u8>;
+/// }
+/// ```
+///
+/// ```compile_fail
+/// # use test_stubs::test_stubs;
+/// #[test_stubs(cfg = "feature = ")]
+/// trait T {
+///     fn f(&self);
+/// }
+/// ```
+///
+/// ```compile_fail
+/// # use test_stubs::test_stubs;
+/// #[test_stubs(macro = panic)]
+/// trait T {
+///     fn f(&self);
+/// }
+/// ```
+struct BadOptionValue;
+
+/// `result = "err"` can't be used with uninhabited error types.
+///
+/// ```compile_fail
+/// # use test_stubs::test_stubs;
+/// #[test_stubs(result = "err", test_only)]
+/// trait T {
+///     fn f(&self) -> Result<u8, std::convert::Infallible>;
+/// }
+/// ```
+struct ResultErrUninhabited;
+
+/// With `strict`, `impl Trait`s which can't be stubbed are errors.
+///
+/// ```compile_fail
+/// # use test_stubs::test_stubs;
+/// #[test_stubs(strict, test_only)]
+/// trait T {
+///     fn f(&self) -> impl std::fmt::Display;
+/// }
+/// ```
+struct Strict;
+
+/// `blanket` requires a trait with supertraits but no items.
+///
+/// ```compile_fail
+/// # use test_stubs::test_stubs;
+/// #[test_stubs(blanket)]
+/// trait T {}
+/// ```
+///
+/// ```compile_fail
+/// # use test_stubs::test_stubs;
+/// trait A {}
+/// #[test_stubs(blanket)]
+/// trait T: A {
+///     fn f(&self);
+/// }
+/// ```
+struct BadBlanket;
+
+/// Methods marked `#[no_stub]` must be implemented.
+///
+/// ```compile_fail
+/// # use test_stubs::test_stubs;
+/// #[test_stubs(test_only)]
+/// trait T {
+///     #[no_stub]
+///     fn f(&self) -> u8;
+///     fn g(&self) -> u8;
+/// }
+///
+/// struct S;
+/// impl T for S {}
+/// ```
+///
+/// ```compile_fail
+/// # use test_stubs::test_stubs;
+/// #[test_stubs(test_only)]
+/// trait T {
+///     #[test_stub(skip)]
+///     fn f(&self) -> u8;
+///     fn g(&self) -> u8;
+/// }
+///
+/// struct S;
+/// impl T for S {}
+/// ```
+///
+/// ```compile_fail
+/// # use test_stubs::test_stubs;
+/// #[test_stubs]
+/// trait T {
+///     #[test_stub(unknown)]
+///     fn f(&self) -> u8;
+/// }
+/// ```
+///
+/// Methods without a body in trait implementations can't be skipped.
+///
+/// ```compile_fail
+/// # use test_stubs::test_stubs;
+/// trait T {
+///     fn f(&self) -> u8;
+/// }
+/// struct S;
+/// #[test_stubs(test_only)]
+/// impl T for S {
+///     #[no_stub]
+///     fn f(&self) -> u8;
+/// }
+/// ```
+struct NoStub;
+
+/// `stub_struct` only accepts a `cfg` option.
+///
+/// ```compile_fail
+/// # use test_stubs::{stub_struct, test_stubs};
+/// #[stub_struct(S, once)]
+/// #[test_stubs(test_only)]
+/// trait T {
+///     fn f(&self) -> u8;
+/// }
+/// ```
+struct StubStructOption;
+
+/// `#[stub_with]` requires a string containing a valid expression, and can't be used with
+/// `#[no_stub]`.
+///
+/// ```compile_fail
+/// # use test_stubs::test_stubs;
+/// #[test_stubs]
+/// trait T {
+///     #[stub_with = "1 +"]
+///     fn f(&self) -> u8;
+/// }
+/// ```
+///
+/// ```compile_fail
+/// # use test_stubs::test_stubs;
+/// #[test_stubs]
+/// trait T {
+///     #[stub_with(1)]
+///     fn f(&self) -> u8;
+/// }
+/// ```
+///
+/// ```compile_fail
+/// # use test_stubs::test_stubs;
+/// #[test_stubs]
+/// trait T {
+///     #[no_stub]
+///     #[stub_with = "1"]
+///     fn f(&self) -> u8;
+/// }
+/// ```
+///
+/// ```compile_fail
+/// # use test_stubs::test_stubs;
+/// #[test_stubs]
+/// trait T {
+///     #[test_stub(skip, expr = 1)]
+///     fn f(&self) -> u8;
+/// }
+/// ```
+struct StubWith;
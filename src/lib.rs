@@ -61,21 +61,507 @@
 //! }
 //! ```
 //!
+//! The same is true for the other iterator traits (e.g. `impl ExactSizeIterator +
+//! DoubleEndedIterator` or `impl IntoIterator`), all of which `std::iter::Empty` implements. If
+//! the `Item` type `T` is given, the stub is instead an empty iterator `std::iter::empty::<T>()`,
+//! which doesn't panic. An `Item` type which itself contains an `impl Trait` can't be named in an
+//! expression, so such iterators are still stubbed with `todo!()`, with nested `impl Iterator`
+//! items becoming `std::iter::Empty` (e.g. `impl Iterator<Item = impl Iterator<Item = u8>>` is
+//! stubbed as `todo!("f") as std::iter::Empty<std::iter::Empty<u8>>`).
+//!
+//! `impl Future<Output = T>` is stubbed as `async move { <stub for T> }`, so the stub only panics
+//! when the future is polled. `async fn`s need no special treatment, since their bodies are already
+//! `async`.
+//!
+//! Similarly, `impl Error` (including forms such as `impl Error + Send + Sync + 'static`) is
+//! stubbed as `todo!("f") as std::fmt::Error`.
+//!
+//! `impl From<T>` and `impl Into<T>` are stubbed as `todo!("f") as T`. If there are other bounds
+//! (e.g. `impl From<String> + Copy`), `T` might not satisfy them, and the stub will not compile.
+//!
+//! `Cow<str>` and `Cow<[T]>` are stubbed as `Cow::Borrowed("")` and `Cow::Borrowed(&[])`
+//! respectively. Other `Cow`s are stubbed with `todo!()` unless the `cow_default` option is used.
+//!
+//! `impl Fn(A) -> B` (and similarly `FnMut` and `FnOnce`) is stubbed as a closure `move |_: A| -> B
+//! { <stub for B> }`, so calling the closure runs the stub for `B`. `Box<dyn Fn(A) -> B>` is
+//! stubbed as the same closure, boxed.
+//!
+//! `Box<dyn Any>` (including with auto-trait bounds such as `Box<dyn Any + Send + Sync>`) is
+//! stubbed as `Box::new(())`, `Box<dyn Error>` as `Box::new(std::fmt::Error)`, and `Box<dyn
+//! Iterator<Item = T>>` as `Box::new(std::iter::empty::<T>())`. Similarly, `Pin<Box<dyn
+//! Future<Output = T>>>` is stubbed as `Box::pin(async move { <stub for T> })`, whereas other
+//! `Pin<Box<T>>` are stubbed as `Box::pin(<stub for T>)`. `Arc<T>` and `Rc<T>` are stubbed in the
+//! same way as `Box<T>`. `Box<str>`, `Arc<str>`, and `Rc<str>` are stubbed as e.g.
+//! `Box::from("")`. Pointers to other trait objects (e.g. `Arc<dyn MyTrait>`) are stubbed with
+//! `todo!()`, since `!` doesn't implement arbitrary traits, so `Arc::new(todo!())` wouldn't
+//! compile.
+//!
+//! `String`, `OsString`, and `PathBuf` are stubbed with their `new()` constructors, `CString` as
+//! `CString::default()`, `&str` (with any lifetime) as `""`, and `&[T]` as `&[]` (unless the
+//! `slice_default` option is used). Other references (e.g. `&T` or `&mut str`) can't be created
+//! out of thin air, so they are stubbed with `todo!()`, which coerces to a reference with any
+//! lifetime. Use [`#[stub_with]`](#method-attributes) if such a stub needs to return a value.
+//!
+//! `NonZero<T>` for integer types `T`, and its aliases (e.g. `NonZeroU32`), are stubbed as
+//! `NonZero::new(1).unwrap()`.
+//!
+//! `Weak<T>` (from either `std::rc` or `std::sync`) is stubbed as `Default::default()`, i.e. a
+//! `Weak` which never upgrades. `OnceCell<T>` and `OnceLock<T>` are stubbed as empty cells with
+//! `new()`, and `RangeInclusive<T>` for integer types `T` as the empty range
+//! `RangeInclusive::new(1, 0)`. `Duration`, `DefaultHasher`, and `RandomState` are stubbed with
+//! `Default::default()` (so a `Duration` stub is zero).
+//!
+//! `MaybeUninit<T>` is stubbed as `MaybeUninit::uninit()`. Note that the value is uninitialized:
+//! the stub itself is safe, but calling `assume_init` (or similar) on it is undefined behaviour.
+//!
+//! Collections (`Vec<T>`, `VecDeque<T>`, `LinkedList<T>`, `HashMap<K, V>`, `HashSet<T>`,
+//! `BTreeMap<K, V>`, and `BTreeSet<T>`) are always stubbed as empty collections (e.g.
+//! `HashMap::new()`), whatever their element types are. No attempt is made to stub the elements, so
+//! element types need not have a stub of their own. `HashMap`s and `HashSet`s with a hasher other
+//! than `RandomState` are stubbed with `Default::default()`, so the hasher must implement
+//! `Default`.
+//!
+//! If a method returns a generic type parameter bounded by `Default` (e.g. `fn f<T: Default>(&self)
+//! -> T`), `test_stubs` will generate `Default::default()`. Type parameters with other bounds (e.g.
+//! `T: From<X>`) fall back to `todo!()`.
+//!
 //! When `test_stubs` has no specific knowledge about a type, it will simply generate `todo!()` and
 //! hope.
 //!
 //! If a trait method takes `self` (rather than `&self`), `test_stubs` will add a `where Self:
-//! Sized` constraint to the `#[cfg(test)]` method.
+//! Sized` constraint to the `#[cfg(test)]` method. Typed receivers such as `self: Box<Self>` or
+//! `self: Rc<Self>` don't need such a constraint.
+//!
+//!
+//! ## `impl` blocks
+//!
+//! `test_stubs` can also be attached to a trait implementation, for traits which don't themselves
+//! use `test_stubs`. Since the macro can't see the trait's definition, the methods to be stubbed
+//! must be declared without a body:
+//!
+//! ```text
+//! #[test_stubs]
+//! impl T for S {
+//!   fn f(&self) { ... }
+//!   fn g(&self);
+//! }
+//! ```
+//!
+//! will produce:
+//!
+//! ```text
+//! impl T for S {
+//!   fn f(&self) { ... }
+//!
+//!   #[cfg(test)]
+//!   fn g(&self) { todo!("g") }
+//! }
+//! ```
+//!
+//! Since `g` is missing outside of tests, this is only useful for implementations that are
+//! themselves only used in tests.
+//!
+//! Methods with a body (in trait implementations or inherent `impl` blocks) can instead be marked
+//! `#[test_stub]`, in which case they keep their body outside of tests, but are stubbed in tests.
+//! This is useful for stubbing out expensive methods:
+//!
+//! ```text
+//! #[test_stubs]
+//! impl S {
+//!   #[test_stub]
+//!   fn f(&self) -> u8 { ... }
+//! }
+//! ```
+//!
+//! will produce:
+//!
+//! ```text
+//! impl S {
+//!   #[cfg(not(test))]
+//!   fn f(&self) -> u8 { ... }
+//!
+//!   #[cfg(test)]
+//!   fn f(&self) -> u8 { todo!("f") }
+//! }
+//! ```
+//!
+//!
+//! ## Method attributes
+//!
+//! A method without a default implementation can be marked `#[no_stub]` (or equivalently
+//! `#[test_stub(skip)]`), in which case it is copied over unchanged (without the `#[no_stub]`
+//! attribute), and must be implemented even in tests. This is useful for methods, such as those
+//! returning `Self`, for which no stub makes sense. In trait implementations, where methods to be
+//! stubbed are declared without a body, marking such a method `#[no_stub]` is an error.
+//!
+//! Alternatively, a method can be marked `#[stub_with = "<expr>"]` (e.g. `#[stub_with =
+//! "MyType::default()"]`) or equivalently `#[test_stub(expr = <expr>)]` (e.g. `#[test_stub(expr =
+//! MyType::default())]`), in which case its stub is `<expr>`, whatever the method's return type.
+//! This can be used for both traits and trait implementations.
+//!
+//!
+//! ## `stub_struct`
+//!
+//! `#[stub_struct(S)]` can be placed alongside `#[test_stubs]` on a trait `T` to generate a
+//! `#[cfg(test)]` struct `S` which implements `T` using its stubs, so that tests don't have to
+//! define their own. If `T` has generic parameters, `S` has the same generic parameters and a
+//! single `PhantomData` field (e.g. for `T<U>`, `struct S<U>(PhantomData<(U,)>)`).
+//!
+//! If `test_stubs` is given a `cfg` option, `stub_struct` must be given the same predicate (e.g.
+//! `#[stub_struct(S, cfg = feature = "mock")]`), since it can't see `test_stubs`' options.
+//!
+//!
+//! ## Options
+//!
+//! `test_stubs` accepts a comma separated list of options, e.g. `#[test_stubs(once)]`:
+//!
+//! * `black_box`: values returned by stubs which don't panic (e.g. `Vec::new()`) are wrapped in
+//!   `std::hint::black_box`, so that the optimiser can't make assumptions about them. This is
+//!   useful for benchmark-like tests.
+//! * `blanket`: for a trait which has supertraits but no items (e.g. `trait C: A + B {}`), generate
+//!   a blanket implementation (`impl<T: A + B> C for T {}`), so that types which implement the
+//!   supertraits automatically implement the trait.
+//! * `cfg = <predicate>` or `cfg = "<predicate>"`: generate stubs under `#[cfg(<predicate>)]` (and
+//!   the other variants under `#[cfg(not(<predicate>))]`) rather than `#[cfg(test)]` (e.g. `cfg =
+//!   feature = "mock"`). This allows stubs to be used in, for example, integration tests in another
+//!   crate. Methods already marked `#[cfg(<predicate>)]` are left unchanged.
+//! * `collections = "new"|"collect"`: by default, collections are stubbed with their `new`
+//!   constructors (e.g. `Vec::new()`). With `collections = "collect"`, they are all stubbed as
+//!   `std::iter::empty().collect()` instead, relying on type inference to pick the collection.
+//! * `count_calls`: a trait `T`'s stubs atomically increment a `static T_CALLS: AtomicUsize` (with
+//!   the trait's name in `UPPER_SNAKE_CASE`) each time they are called, including those that then
+//!   panic. Since the counter is shared between threads, this allows tests to check the total
+//!   number of calls made to stubs from multiple threads. This option has no effect on trait
+//!   implementations.
+//! * `cow_default = "<expr>"`: stub `Cow<T>` returns with `<expr>` (e.g. `cow_default =
+//!   "Cow::Owned(Default::default())"`). `Cow<str>` and `Cow<[T]>` are still stubbed as
+//!   `Cow::Borrowed("")` and `Cow::Borrowed(&[])`.
+//! * `debug_args`: panic messages include the `Debug` representation of the method's arguments
+//!   (e.g. `not yet implemented: f(x = 1, y = "a")`), so all arguments must implement `Debug`.
+//!   Arguments which are not simple identifiers (e.g. `_`) are not included. Stubs that don't
+//!   panic don't use their arguments, so stubs always allow `unused_variables`.
+//! * `default`: types which aren't otherwise special cased (e.g. user-defined structs, or `u8`) are
+//!   stubbed with `Default::default()` rather than panicking, so they must implement `Default`.
+//!   Generic type parameters and associated types (e.g. `Self::Item`) are still stubbed with
+//!   `todo!()`.
+//! * `echo`: if a method has an argument whose type is the same as the method's return type (e.g.
+//!   `fn timeout(&self, d: Duration) -> Duration`), the stub returns that argument. Types are
+//!   compared syntactically, so e.g. `Duration` and `std::time::Duration` are considered different.
+//!   Types with elided lifetimes (e.g. `&str`, `Cow<str>`, or `Cow<'_, str>`) are never echoed,
+//!   since the returned lifetime may be tied to `&self` rather than to the argument. Other types
+//!   with hidden lifetimes (e.g. `MyRef<u8>` for `struct MyRef<'a, T>`) can't be recognised, so
+//!   their lifetimes must be written out (e.g. `MyRef<'_, u8>`) for them not to be echoed.
+//!   Arguments which are not simple identifiers (e.g. `_`) are never returned.
+//! * `enum_default(<type> = "<expr>", ...)`: stub returns of the user-defined type `<type>`
+//!   (typically an enum) with `<expr>` (e.g. `enum_default(MyEnum = "MyEnum::None")`). Types are
+//!   matched on the last segment of their path, so `MyEnum` also matches `crate::MyEnum`.
+//! * `impl_for = "<type>"`: generate a `#[cfg(test)]` implementation of the trait for `<type>`
+//!   (e.g. `impl_for = "MyTestType"`), which must be defined elsewhere. This saves writing `impl
+//!   T for MyTestType {}` in tests.
+//! * `macro = todo|unimplemented` or `macro = "todo"|"unimplemented"`: the macro stubs use to
+//!   panic. This overrides the `TEST_STUBS_MACRO` environment variable (see below).
+//! * `once`: a stub panics if it is called more than once on the same thread. This is useful for
+//!   detecting unexpected repeated calls to stubs which don't themselves panic.
+//! * `result = "ok"|"err"`: by default, `Result<T, E>` is stubbed as `Ok(<stub for T>)`. With
+//!   `result = "err"`, it is stubbed as `Err(Default::default())` instead, so `E` must implement
+//!   `Default`. If `E` is an `impl Trait` (e.g. `impl std::error::Error`), `Err` wraps the normal
+//!   stub for `E`, which panics. Since `Result<T, Infallible>` can't be `Err`, using
+//!   `result = "err"` with it is a compile-time error.
+//! * `seed = <u64>`: stubs for primitive numeric and `bool` types (including when nested, e.g. in
+//!   `Option<u8>`) return pseudo-random values rather than panicking. The values are derived from
+//!   the seed and the method's name, so they are the same on every compilation. This makes stub
+//!   values distinguishable, and can catch code that accidentally relies on values being zero.
+//! * `slice_default = "<expr>"`: stub `&[T]` returns with `<expr>` rather than `&[]` (e.g.
+//!   `slice_default = "&[1, 2, 3]"`). Since the expression is used for every `&[T]`, whatever `T`
+//!   is, this is most useful for traits whose slices all have the same element type.
+//! * `strict`: an `impl Trait` return type which `test_stubs` doesn't know how to stub (i.e. one
+//!   which would be stubbed with `todo!()` and hope, e.g. `impl Display`) is a compile-time error
+//!   naming the method, rather than a (possibly confusing) type inference error in the generated
+//!   code. `impl Trait`s with only auto traits (e.g. `impl Send + Sync`) are still stubbed with
+//!   `todo!()`, which satisfies them.
+//! * `stubbed_methods`: generate a `const T_STUBBED_METHODS: &[&str]` (with the trait `T`'s name in
+//!   `UPPER_SNAKE_CASE`) listing the names of the methods which were given stubs, in the order they
+//!   are defined. Methods marked `#[no_stub]` or already marked `#[cfg(test)]` are not included.
+//!   This option has no effect on trait implementations.
+//! * `test_only`: the trait is only ever used in tests (e.g. it is a test fixture), so stubs are
+//!   generated without any `cfg` gating and no `#[cfg(not(test))]` variants are generated. In other
+//!   words, every method without a default implementation is given a stub implementation. For
+//!   example, this compiles even outside tests:
+//!
+//!   ```rust
+//!   # use test_stubs::test_stubs;
+//!   #[test_stubs(test_only)]
+//!   trait Fixture {
+//!       fn f(&self) -> u8;
+//!   }
+//!
+//!   struct S;
+//!   impl Fixture for S {}
+//!   ```
+//! * `uninit`: stubs for primitive integer and float types (including when nested, e.g. in
+//!   `Option<u8>`) return `unsafe { MaybeUninit::uninit().assume_init() }` rather than panicking.
+//!   **This is undefined behaviour**: it is only intended for tests that need a value but never
+//!   look at it, and which are willing to accept that the compiler may miscompile them.
+//! * `vec_capacity = <usize>`: `Vec` stubs are created with `Vec::with_capacity(<usize>)` rather
+//!   than `Vec::new()` (even with `collections = "collect"`). The `Vec` is still empty.
+//! * `warn_silent`: some stubs (e.g. for `VecDeque<T>`) return a value rather than panicking,
+//!   which can hide the fact that a method hasn't been implemented. This option makes a trait `T`
+//!   whose stubs include such "silent" stubs emit a deprecation warning listing them. The list is
+//!   also available as `const T_SILENT_STUBS: &[&str]` (with the trait's name in
+//!   `UPPER_SNAKE_CASE`).
+//!
+//! Some defaults can be set crate-wide with environment variables, which are read when the macro is
+//! expanded (e.g. via the `[env]` section of `.cargo/config.toml`):
+//!
+//! * `TEST_STUBS_MACRO`: the macro stubs use to panic, either `todo` (the default) or
+//!   `unimplemented`.
+//!
+//! Note that Cargo does not know that `test_stubs` reads these variables, so changing them may
+//! require a `cargo clean`.
+use std::cell::Cell;
+
 use proc_macro::TokenStream;
-use quote::quote;
+use proc_macro2::{Literal, Span, TokenTree};
+use quote::{ToTokens, quote};
+use syn::ext::IdentExt;
+use syn::parse::{ParseStream, Parser};
+use syn::punctuated::Punctuated;
 use syn::{
-    FnArg, GenericArgument, ItemTrait, Meta, PathArguments, ReturnType, TraitItem, Type,
-    TypeImplTrait, TypeParamBound, WherePredicate, parse_macro_input,
+    AngleBracketedGenericArguments, Attribute, Block, Expr, ExprLit, FnArg, GenericArgument,
+    GenericParam, Generics, Ident, ImplItem, ImplItemFn, Item, ItemImpl, ItemTrait, Lit, LitInt,
+    LitStr, Meta, ParenthesizedGenericArguments, Pat, PatType, Path, PathArguments, ReturnType,
+    Signature, Token, TraitItem, TraitItemFn, Type, TypeImplTrait, TypeParamBound, TypePath,
+    Visibility, WherePredicate, parse_macro_input,
 };
 
+#[cfg(doctest)]
+mod compile_fail;
+
+#[proc_macro_attribute]
+pub fn test_stubs(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let config = match parse_config(attr.into()) {
+        Ok(x) => x,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    match parse_macro_input!(item as Item) {
+        Item::Trait(trait_item) => stub_trait(&config, trait_item),
+        Item::Impl(impl_item) => stub_impl(&config, impl_item),
+        x => syn::Error::new_spanned(
+            x,
+            "`test_stubs` can only be applied to traits and impl blocks",
+        )
+        .to_compile_error(),
+    }
+    .into()
+}
+
+/// Parse the options passed to `#[test_stubs(...)]`.
+fn parse_config(attr: proc_macro2::TokenStream) -> syn::Result<Config> {
+    let mut config = Config::from_env()?;
+    let config_parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("black_box") {
+            config.black_box = true;
+            Ok(())
+        } else if meta.path.is_ident("blanket") {
+            config.blanket = true;
+            Ok(())
+        } else if meta.path.is_ident("count_calls") {
+            config.count_calls = true;
+            Ok(())
+        } else if meta.path.is_ident("cow_default") {
+            config.cow_default = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("cfg") {
+            // The predicate can be given either as a string or directly.
+            let value = meta.value()?;
+            config.cfg = if value.peek(LitStr) {
+                value.parse::<LitStr>()?.parse()?
+            } else {
+                value.parse()?
+            };
+            Ok(())
+        } else if meta.path.is_ident("collections") {
+            let lit = meta.value()?.parse::<LitStr>()?;
+            config.collections_collect = match lit.value().as_str() {
+                "new" => false,
+                "collect" => true,
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        "expected `\"new\"` or `\"collect\"`",
+                    ));
+                }
+            };
+            Ok(())
+        } else if meta.path.is_ident("debug_args") {
+            config.debug_args = true;
+            Ok(())
+        } else if meta.path.is_ident("default") {
+            config.default = true;
+            Ok(())
+        } else if meta.path.is_ident("echo") {
+            config.echo = true;
+            Ok(())
+        } else if meta.path.is_ident("enum_default") {
+            meta.parse_nested_meta(|meta| {
+                let Some(ident) = meta.path.get_ident().cloned() else {
+                    return Err(meta.error("expected an enum name"));
+                };
+                let expr = meta.value()?.parse::<LitStr>()?.parse()?;
+                config.enum_defaults.push((ident, expr));
+                Ok(())
+            })
+        } else if meta.path.is_ident("impl_for") {
+            config.impl_for = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("macro") {
+            // The macro can be given either as a string or directly.
+            let value = meta.value()?;
+            let ident = if value.peek(LitStr) {
+                value.parse::<LitStr>()?.parse_with(Ident::parse_any)?
+            } else {
+                value.call(Ident::parse_any)?
+            };
+            if ident != "todo" && ident != "unimplemented" {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "expected `todo` or `unimplemented`",
+                ));
+            }
+            config.panic_macro = ident;
+            Ok(())
+        } else if meta.path.is_ident("once") {
+            config.once = true;
+            Ok(())
+        } else if meta.path.is_ident("result") {
+            let lit = meta.value()?.parse::<LitStr>()?;
+            config.result_err = match lit.value().as_str() {
+                "ok" => false,
+                "err" => true,
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        "expected `\"ok\"` or `\"err\"`",
+                    ));
+                }
+            };
+            Ok(())
+        } else if meta.path.is_ident("seed") {
+            config.seed = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+            Ok(())
+        } else if meta.path.is_ident("slice_default") {
+            config.slice_default = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("strict") {
+            config.strict = true;
+            Ok(())
+        } else if meta.path.is_ident("stubbed_methods") {
+            config.stubbed_methods = true;
+            Ok(())
+        } else if meta.path.is_ident("test_only") {
+            config.test_only = true;
+            Ok(())
+        } else if meta.path.is_ident("uninit") {
+            config.uninit = true;
+            Ok(())
+        } else if meta.path.is_ident("vec_capacity") {
+            config.vec_capacity = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+            Ok(())
+        } else if meta.path.is_ident("warn_silent") {
+            config.warn_silent = true;
+            Ok(())
+        } else {
+            Err(meta.error("unknown `test_stubs` option"))
+        }
+    });
+    config_parser.parse2(attr)?;
+    Ok(config)
+}
+
 #[proc_macro_attribute]
-pub fn test_stubs(_attr: TokenStream, item: TokenStream) -> TokenStream {
-    let mut trait_item = parse_macro_input!(item as ItemTrait);
+pub fn stub_struct(attr: TokenStream, item: TokenStream) -> TokenStream {
+    // `#[stub_struct(S)]` or `#[stub_struct(S, cfg = <predicate>)]`, where the predicate must match
+    // the one given to `test_stubs`.
+    let attr_parser = |input: ParseStream| -> syn::Result<(Ident, Meta)> {
+        let name = input.parse::<Ident>()?;
+        let mut cfg = syn::parse_quote!(test);
+        if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let key = input.parse::<Ident>()?;
+            if key != "cfg" {
+                return Err(syn::Error::new_spanned(key, "expected `cfg`"));
+            }
+            input.parse::<Token![=]>()?;
+            // As with `test_stubs`, the predicate can be given either as a string or directly.
+            cfg = if input.peek(LitStr) {
+                input.parse::<LitStr>()?.parse()?
+            } else {
+                input.parse()?
+            };
+        }
+        Ok((name, cfg))
+    };
+    let (name, cfg) = match attr_parser.parse(attr) {
+        Ok(x) => x,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let trait_item = parse_macro_input!(item as ItemTrait);
+    let vis = &trait_item.vis;
+    let unsafety = &trait_item.unsafety;
+    let ident = &trait_item.ident;
+    let (impl_generics, ty_generics, where_clause) = trait_item.generics.split_for_impl();
+    // Generic parameters must be used in the struct, so we give it a `PhantomData` field.
+    let phantoms = trait_item
+        .generics
+        .params
+        .iter()
+        .filter_map(|x| match x {
+            GenericParam::Lifetime(x) => {
+                let lt = &x.lifetime;
+                Some(quote! { &#lt () })
+            }
+            GenericParam::Type(x) => {
+                let ident = &x.ident;
+                Some(quote! { #ident })
+            }
+            GenericParam::Const(_) => None,
+        })
+        .collect::<Vec<_>>();
+    let fields = if phantoms.is_empty() {
+        quote!()
+    } else {
+        quote! { (#vis ::core::marker::PhantomData<(#(#phantoms,)*)>) }
+    };
+    quote! {
+        #trait_item
+        #[cfg(#cfg)]
+        #vis struct #name #impl_generics #fields #where_clause;
+        #[cfg(#cfg)]
+        #unsafety impl #impl_generics #ident #ty_generics for #name #ty_generics #where_clause {}
+    }
+    .into()
+}
+
+/// Generate the `#[cfg(not(test))]` and `#[cfg(test)]` variants of each method without a default
+/// implementation in `trait_item`.
+fn stub_trait(config: &Config, mut trait_item: ItemTrait) -> proc_macro2::TokenStream {
+    let blanket = if config.blanket {
+        match blanket_impl(config, &trait_item) {
+            Ok(x) => x,
+            Err(e) => return e.to_compile_error(),
+        }
+    } else {
+        quote!()
+    };
+    let impl_for = match &config.impl_for {
+        Some(ty) => impl_for(config, &trait_item, ty),
+        None => quote!(),
+    };
+
+    let counter = Ident::new(
+        &format!("{}_CALLS", upper_snake_case(&trait_item.ident)),
+        Span::call_site(),
+    );
 
     // rustc complains that the trait we attach to is unused, so silence it by attaching
     // `unreachable_code` to the trait.
@@ -84,38 +570,54 @@ pub fn test_stubs(_attr: TokenStream, item: TokenStream) -> TokenStream {
         .push(syn::parse_quote!(#[allow(unreachable_code)]));
 
     let mut new_items = Vec::with_capacity(trait_item.items.len());
+    // The names of all stubbed methods.
+    let mut stubbed = Vec::new();
+    // The names of methods whose stubs never panic.
+    let mut silent = Vec::new();
     for item in trait_item.items.into_iter() {
         match item {
             TraitItem::Fn(mut meth) if meth.default.is_none() => {
+                let meth_attrs = match MethodAttrs::take(&mut meth.attrs) {
+                    Ok(x) => x,
+                    Err(e) => return e.to_compile_error(),
+                };
+
+                // Methods marked `#[no_stub]` are never stubbed, so must always be implemented.
+                if meth_attrs.no_stub.is_some() {
+                    new_items.push(TraitItem::Fn(meth));
+                    continue;
+                }
+
                 // If the method is already `#[cfg(test)]`, there is nothing to duplicate.
+                let cfg = config.cfg.to_token_stream().to_string();
                 if meth.attrs.iter().any(|x| {
-                    x.path().is_ident("cfg")
-                        && matches!(&x.meta, Meta::List(y) if y.path.is_ident("test"))
+                    matches!(&x.meta, Meta::List(y)
+                        if y.path.is_ident("cfg") && y.tokens.to_string() == cfg)
                 }) {
                     new_items.push(TraitItem::Fn(meth));
                     continue;
                 }
 
                 // The `#[cfg(not(test))]` variant.
-                let mut not_test = meth.clone();
-                not_test.attrs.push(syn::parse_quote!(#[cfg(not(test))]));
-                new_items.push(TraitItem::Fn(not_test));
+                if !config.test_only {
+                    let mut not_test = meth.clone();
+                    let cfg = &config.cfg;
+                    not_test.attrs.push(syn::parse_quote!(#[cfg(not(#cfg))]));
+                    new_items.push(TraitItem::Fn(not_test));
+                }
 
                 // The `#[cfg(test)]` variant.
-                meth.attrs.push(syn::parse_quote!(#[cfg(test)]));
-
-                // Silence warnings about unused parameters.
-                meth.attrs
-                    .push(syn::parse_quote!(#[allow(unused_variables)]));
-                // Silence warnings about `todo!()` being unusable code.
-                meth.attrs
-                    .push(syn::parse_quote!(#[allow(unreachable_code)]));
+                meth.attrs.extend(config.test_cfg());
+                push_stub_allows(config, &mut meth.attrs);
 
                 // If the self type is `self`, we have to ensure `where Self: Sized` is part of the
-                // `where` predicates.
+                // `where` predicates. Typed receivers such as `self: Box<Self>` or `self: Rc<Self>`
+                // are pointers, so they don't need `Self` to be `Sized`.
                 if matches!(
                     meth.sig.inputs.first(),
                     Some(FnArg::Receiver(recv)) if recv.reference.is_none()
+                        && (recv.colon_token.is_none()
+                            || matches!(&*recv.ty, Type::Path(p) if p.path.is_ident("Self")))
                 ) {
                     let wheres = meth.sig.generics.make_where_clause();
                     // Search for `where Self:Sized`, adding it if not present.
@@ -124,15 +626,18 @@ pub fn test_stubs(_attr: TokenStream, item: TokenStream) -> TokenStream {
                     }
                 }
 
-                let name = meth.sig.ident.to_string();
-                let stubexpr = match &meth.sig.output {
-                    ReturnType::Default => {
-                        quote! { todo!(#name) }
-                    }
-                    ReturnType::Type(_, ty) => stub_expr_for_ty(ty, &name),
-                };
-                meth.default = Some(syn::parse_quote!({ #stubexpr }));
-
+                let (block, panics) = stub_block(
+                    config,
+                    &meth.sig,
+                    &trait_item.generics,
+                    meth_attrs.stub_with.as_ref(),
+                    config.count_calls.then_some(&counter),
+                );
+                stubbed.push(meth.sig.ident.to_string());
+                if !panics {
+                    silent.push(meth.sig.ident.to_string());
+                }
+                meth.default = Some(block);
                 new_items.push(TraitItem::Fn(meth));
             }
             x => new_items.push(x),
@@ -140,7 +645,544 @@ pub fn test_stubs(_attr: TokenStream, item: TokenStream) -> TokenStream {
     }
 
     trait_item.items = new_items;
-    TokenStream::from(quote!(#trait_item))
+    let mut out = quote!(#trait_item #blanket #impl_for);
+    if config.count_calls {
+        let vis = &trait_item.vis;
+        let test_cfg = config.test_cfg();
+        out.extend(quote! {
+            #test_cfg
+            #vis static #counter: ::core::sync::atomic::AtomicUsize =
+                ::core::sync::atomic::AtomicUsize::new(0);
+        });
+    }
+    if config.stubbed_methods {
+        let vis = &trait_item.vis;
+        let list_name = Ident::new(
+            &format!("{}_STUBBED_METHODS", upper_snake_case(&trait_item.ident)),
+            Span::call_site(),
+        );
+        let test_cfg = config.test_cfg();
+        out.extend(quote! {
+            #test_cfg
+            #vis const #list_name: &[&str] = &[#(#stubbed),*];
+        });
+    }
+    if config.warn_silent && !silent.is_empty() {
+        // Using a deprecated item is the only way that a proc macro can emit a warning on stable
+        // Rust.
+        let vis = &trait_item.vis;
+        let list_name = Ident::new(
+            &format!("{}_SILENT_STUBS", upper_snake_case(&trait_item.ident)),
+            Span::call_site(),
+        );
+        let note = format!(
+            "the stubs for {} in `{}` never panic",
+            silent
+                .iter()
+                .map(|x| format!("`{x}`"))
+                .collect::<Vec<_>>()
+                .join(", "),
+            trait_item.ident
+        );
+        let test_cfg = config.test_cfg();
+        out.extend(quote! {
+            #test_cfg
+            #[deprecated(note = #note)]
+            #vis const #list_name: &[&str] = &[#(#silent),*];
+            #test_cfg
+            const _: &[&str] = #list_name;
+        });
+    }
+    out
+}
+
+/// Generate a `#[cfg(test)]` blanket implementation of `trait_item`, which must have supertraits
+/// but no items, for every type which implements its supertraits.
+fn blanket_impl(config: &Config, trait_item: &ItemTrait) -> syn::Result<proc_macro2::TokenStream> {
+    if trait_item.supertraits.is_empty() || !trait_item.items.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &trait_item.ident,
+            "`blanket` can only be used with traits which have supertraits but no items",
+        ));
+    }
+    let test_cfg = config.test_cfg();
+    let unsafety = &trait_item.unsafety;
+    let ident = &trait_item.ident;
+    let supertraits = &trait_item.supertraits;
+    let mut generics = trait_item.generics.clone();
+    generics
+        .params
+        .push(syn::parse_quote!(__TestStubsT: ?Sized + #supertraits));
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = trait_item.generics.split_for_impl();
+    Ok(quote! {
+        #test_cfg
+        #unsafety impl #impl_generics #ident #ty_generics for __TestStubsT #where_clause {}
+    })
+}
+
+/// Generate a `#[cfg(test)]` implementation of `trait_item` for the user-defined type `ty`, relying
+/// on the trait's stubs. If the trait is generic, `ty` implements it for all its type arguments.
+fn impl_for(config: &Config, trait_item: &ItemTrait, ty: &Type) -> proc_macro2::TokenStream {
+    let test_cfg = config.test_cfg();
+    let unsafety = &trait_item.unsafety;
+    let ident = &trait_item.ident;
+    let (impl_generics, ty_generics, where_clause) = trait_item.generics.split_for_impl();
+    quote! {
+        #test_cfg
+        #unsafety impl #impl_generics #ident #ty_generics for #ty #where_clause {}
+    }
+}
+
+/// Generate `#[cfg(test)]` stubs for each method in the trait implementation `impl_item` which is
+/// declared without a body (e.g. `fn f(&self);`). Since a proc macro can't see the trait's
+/// definition, the methods to be stubbed must be listed in this way. Methods with a body (in both
+/// trait and inherent implementations) which are marked `#[test_stub]` are replaced by a stub in
+/// tests, keeping their body outside of tests.
+fn stub_impl(config: &Config, mut impl_item: ItemImpl) -> proc_macro2::TokenStream {
+    let mut new_items = Vec::with_capacity(impl_item.items.len());
+    for item in impl_item.items.into_iter() {
+        match item {
+            ImplItem::Fn(mut meth) => {
+                let meth_attrs = match MethodAttrs::take(&mut meth.attrs) {
+                    Ok(x) => x,
+                    Err(e) => return e.to_compile_error(),
+                };
+                if meth_attrs.no_stub.is_some() || !meth_attrs.stub {
+                    new_items.push(ImplItem::Fn(meth));
+                    continue;
+                }
+
+                // The `#[cfg(not(test))]` variant keeps the method's body.
+                if !config.test_only {
+                    let mut not_test = meth.clone();
+                    let cfg = &config.cfg;
+                    not_test.attrs.push(syn::parse_quote!(#[cfg(not(#cfg))]));
+                    new_items.push(ImplItem::Fn(not_test));
+                }
+
+                meth.attrs.extend(config.test_cfg());
+                push_stub_allows(config, &mut meth.attrs);
+                let (block, _) = stub_block(
+                    config,
+                    &meth.sig,
+                    &impl_item.generics,
+                    meth_attrs.stub_with.as_ref(),
+                    None,
+                );
+                meth.block = block;
+                new_items.push(ImplItem::Fn(meth));
+            }
+            // `syn` parses methods without a body as verbatim tokens. Inherent methods must always
+            // have a body, so these are only stubbed in trait implementations.
+            ImplItem::Verbatim(toks) if impl_item.trait_.is_some() => {
+                match syn::parse2::<TraitItemFn>(toks.clone()) {
+                    Ok(meth) if meth.default.is_none() => {
+                        let mut attrs = meth.attrs;
+                        let meth_attrs = match MethodAttrs::take(&mut attrs) {
+                            Ok(x) => x,
+                            Err(e) => return e.to_compile_error(),
+                        };
+                        // Declaring a method without a body only makes sense if it is to be
+                        // stubbed.
+                        if let Some(no_stub) = meth_attrs.no_stub {
+                            return syn::Error::new_spanned(
+                                no_stub,
+                                "a method without a body can't be skipped: implement it instead",
+                            )
+                            .to_compile_error();
+                        }
+                        attrs.extend(config.test_cfg());
+                        push_stub_allows(config, &mut attrs);
+                        let (block, _) = stub_block(
+                            config,
+                            &meth.sig,
+                            &impl_item.generics,
+                            meth_attrs.stub_with.as_ref(),
+                            None,
+                        );
+                        new_items.push(ImplItem::Fn(ImplItemFn {
+                            attrs,
+                            vis: Visibility::Inherited,
+                            defaultness: None,
+                            sig: meth.sig,
+                            block,
+                        }));
+                    }
+                    _ => new_items.push(ImplItem::Verbatim(toks)),
+                }
+            }
+            x => new_items.push(x),
+        }
+    }
+
+    impl_item.items = new_items;
+    quote!(#impl_item)
+}
+
+/// Add the `allow`s needed to silence warnings about a stub method to `attrs`.
+fn push_stub_allows(config: &Config, attrs: &mut Vec<Attribute>) {
+    // Silence warnings about unused parameters.
+    attrs.push(syn::parse_quote!(#[allow(unused_variables)]));
+    // Silence warnings about `todo!()` being unusable code.
+    attrs.push(syn::parse_quote!(#[allow(unreachable_code)]));
+    if config.uninit {
+        // The user has explicitly opted in to uninitialized values.
+        attrs.push(syn::parse_quote!(#[allow(invalid_value, clippy::uninit_assumed_init)]));
+    }
+}
+
+/// The per-method attributes which control how a method is stubbed.
+struct MethodAttrs {
+    /// The `#[no_stub]` or `#[test_stub(skip)]` attribute, if there is one.
+    no_stub: Option<Attribute>,
+    /// The expression given by `#[stub_with = "<expr>"]` or `#[test_stub(expr = <expr>)]`, if
+    /// there is one.
+    stub_with: Option<Expr>,
+    /// Was the method marked `#[test_stub]` (with or without an expression), or `#[stub_with]`?
+    /// Methods with a body are only stubbed if so.
+    stub: bool,
+}
+
+impl MethodAttrs {
+    /// Remove the per-method attributes from `attrs`, returning them.
+    fn take(attrs: &mut Vec<Attribute>) -> syn::Result<Self> {
+        let mut no_stub = None;
+        let mut stub_with = None;
+        let mut stub = false;
+        let mut rest = Vec::with_capacity(attrs.len());
+        for attr in attrs.drain(..) {
+            if attr.path().is_ident("no_stub") {
+                no_stub = Some(attr);
+            } else if attr.path().is_ident("stub_with") {
+                if let Meta::NameValue(nv) = &attr.meta
+                    && let Expr::Lit(ExprLit {
+                        lit: Lit::Str(lit), ..
+                    }) = &nv.value
+                {
+                    stub_with = Some(lit.parse()?);
+                } else {
+                    return Err(syn::Error::new_spanned(
+                        attr,
+                        "expected `#[stub_with = \"<expr>\"]`",
+                    ));
+                }
+            } else if attr.path().is_ident("test_stub") && matches!(attr.meta, Meta::Path(_)) {
+                stub = true;
+            } else if attr.path().is_ident("test_stub") {
+                let mut skip = false;
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("skip") {
+                        skip = true;
+                        Ok(())
+                    } else if meta.path.is_ident("expr") {
+                        stub_with = Some(meta.value()?.parse()?);
+                        Ok(())
+                    } else {
+                        Err(meta.error("unknown `test_stub` option"))
+                    }
+                })?;
+                if skip {
+                    no_stub = Some(attr);
+                }
+            } else {
+                rest.push(attr);
+            }
+        }
+        *attrs = rest;
+        if let Some(no_stub) = &no_stub
+            && stub_with.is_some()
+        {
+            return Err(syn::Error::new_spanned(
+                no_stub,
+                "a method can't both be skipped and have a stub expression",
+            ));
+        }
+        Ok(MethodAttrs {
+            no_stub,
+            stub: stub || stub_with.is_some(),
+            stub_with,
+        })
+    }
+}
+
+/// Generate the stub body for a method with signature `sig` in a trait or impl with generics
+/// `generics`. If `stub_with` is set, it is used as the stub's expression. If `counter` is set, the
+/// stub increments the `AtomicUsize` static of that name. Also returns `true` if the stub might
+/// panic or `false` if it never panics.
+fn stub_block(
+    config: &Config,
+    sig: &Signature,
+    generics: &Generics,
+    stub_with: Option<&Expr>,
+    counter: Option<&Ident>,
+) -> (Block, bool) {
+    let name = sig.ident.to_string();
+    let panic_macro = &config.panic_macro;
+    let todo = if config.debug_args {
+        // Arguments which aren't simple identifiers (e.g. `_` or `(a, b)`) aren't printed.
+        let args = sig
+            .inputs
+            .iter()
+            .filter_map(|x| match x {
+                FnArg::Typed(PatType { pat, .. }) => match &**pat {
+                    Pat::Ident(x) => Some(&x.ident),
+                    _ => None,
+                },
+                FnArg::Receiver(_) => None,
+            })
+            .collect::<Vec<_>>();
+        let fmt = format!(
+            "{name}({})",
+            args.iter()
+                .map(|x| format!("{} = {{:?}}", x.unraw()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        quote! { ::core::#panic_macro!(#fmt, #(#args),*) }
+    } else {
+        quote! { ::core::#panic_macro!(#name) }
+    };
+    let ctx = StubCtx {
+        config,
+        todo_expr: todo,
+        panics: Cell::new(false),
+        literals: Cell::new(0),
+        name,
+        default_params: default_params(generics)
+            .chain(default_params(&sig.generics))
+            .cloned()
+            .collect(),
+        type_params: generics
+            .type_params()
+            .chain(sig.generics.type_params())
+            .map(|tp| tp.ident.clone())
+            .collect(),
+    };
+    let stubexpr = match &sig.output {
+        _ if let Some(x) = stub_with => quote! { #x },
+        ReturnType::Default => ctx.todo(),
+        ReturnType::Type(_, ty)
+            if config.echo
+                && let Some(arg) = echo_arg(sig, ty) =>
+        {
+            quote! { #arg }
+        }
+        ReturnType::Type(_, ty) => stub_expr_for_ty(ty, &ctx),
+    };
+    let stubexpr = if config.black_box && !ctx.panics.get() {
+        quote! { ::core::hint::black_box(#stubexpr) }
+    } else {
+        stubexpr
+    };
+    let stubexpr = if config.once {
+        let name = &ctx.name;
+        // Each stub has its own thread local, so calls are tracked per method.
+        quote! {
+            ::std::thread_local! {
+                static CALLED: ::core::cell::Cell<bool> = const { ::core::cell::Cell::new(false) };
+            }
+            if CALLED.with(|x| x.replace(true)) {
+                ::core::panic!("{} called more than once", #name);
+            }
+            #stubexpr
+        }
+    } else {
+        stubexpr
+    };
+    let stubexpr = match counter {
+        Some(counter) => quote! {
+            #counter.fetch_add(1, ::core::sync::atomic::Ordering::Relaxed);
+            #stubexpr
+        },
+        None => stubexpr,
+    };
+    (syn::parse_quote!({ #stubexpr }), ctx.panics.get())
+}
+
+/// Return the first argument of `sig` which is a simple identifier (e.g. `x` but not `_`) and whose
+/// type is syntactically identical to `ret_ty`. Since argument position `impl Trait` types are
+/// distinct from return position `impl Trait` types, such types never match. Nor do types with
+/// elided lifetimes (e.g. `&str`): in the return type they may be tied to `&self`, in which case
+/// returning the argument wouldn't compile.
+fn echo_arg<'a>(sig: &'a Signature, ret_ty: &Type) -> Option<&'a Ident> {
+    if contains_impl_trait(ret_ty) || contains_elided_lifetime(ret_ty) {
+        return None;
+    }
+    let ret_ty = ret_ty.to_token_stream().to_string();
+    sig.inputs.iter().find_map(|x| match x {
+        FnArg::Typed(PatType { pat, ty, .. })
+            if let Pat::Ident(x) = &**pat
+                && ty.to_token_stream().to_string() == ret_ty =>
+        {
+            Some(&x.ident)
+        }
+        _ => None,
+    })
+}
+
+/// The options passed to `#[test_stubs(...)]`.
+struct Config {
+    /// Should the values returned by stubs which don't panic be wrapped in
+    /// `std::hint::black_box`?
+    black_box: bool,
+    /// Should a blanket implementation be generated for a trait with only supertraits?
+    blanket: bool,
+    /// The `cfg` predicate under which stubs are generated.
+    cfg: Meta,
+    /// Should collection stubs use `std::iter::empty().collect()` rather than `::new()`?
+    collections_collect: bool,
+    /// Should stubs count how many times they are called?
+    count_calls: bool,
+    /// The expression to use for `Cow` stubs.
+    cow_default: Option<Expr>,
+    /// Should panic messages include the `Debug` representation of the method's arguments?
+    debug_args: bool,
+    /// Should types which aren't special cased be stubbed with `Default::default()`?
+    default: bool,
+    /// Should stubs return an argument whose type is the same as the return type?
+    echo: bool,
+    /// User-defined types (e.g. enums) and the expressions to stub them with.
+    enum_defaults: Vec<(Ident, Expr)>,
+    /// A type for which a `#[cfg(test)]` implementation of the trait should be generated.
+    impl_for: Option<Type>,
+    /// Should stubs panic if they are called more than once?
+    once: bool,
+    /// Should integer and float stubs return uninitialized values rather than panicking?
+    uninit: bool,
+    /// Should `Result` stubs return `Err` rather than `Ok`?
+    result_err: bool,
+    /// If set, primitive numeric and `bool` stubs return pseudo-random values derived from this
+    /// seed.
+    seed: Option<u64>,
+    /// The expression to use for `&[T]` stubs.
+    slice_default: Option<Expr>,
+    /// Should `impl Trait` returns which aren't special cased be a compile-time error?
+    strict: bool,
+    /// Should a list of the stubbed methods' names be generated?
+    stubbed_methods: bool,
+    /// Is the trait only used in tests, in which case stubs are generated without `cfg` gating?
+    test_only: bool,
+    /// If set, `Vec` stubs are created with this capacity.
+    vec_capacity: Option<usize>,
+    /// Should a warning be emitted listing the stubs which never panic?
+    warn_silent: bool,
+    /// The macro stubs use to panic: either `todo` or `unimplemented`.
+    panic_macro: Ident,
+}
+
+impl Config {
+    /// Create the default [Config], taking into account crate-wide defaults set by environment
+    /// variables when the macro is expanded.
+    fn from_env() -> syn::Result<Self> {
+        let panic_macro = match std::env::var("TEST_STUBS_MACRO") {
+            Ok(x) => match x.as_str() {
+                "todo" | "unimplemented" => Ident::new(&x, Span::call_site()),
+                _ => {
+                    return Err(syn::Error::new(
+                        Span::call_site(),
+                        format!("TEST_STUBS_MACRO must be `todo` or `unimplemented`, not `{x}`"),
+                    ));
+                }
+            },
+            Err(_) => Ident::new("todo", Span::call_site()),
+        };
+        Ok(Config {
+            black_box: false,
+            blanket: false,
+            cfg: syn::parse_quote!(test),
+            collections_collect: false,
+            count_calls: false,
+            cow_default: None,
+            debug_args: false,
+            default: false,
+            echo: false,
+            enum_defaults: Vec::new(),
+            impl_for: None,
+            once: false,
+            uninit: false,
+            result_err: false,
+            seed: None,
+            slice_default: None,
+            strict: false,
+            stubbed_methods: false,
+            test_only: false,
+            vec_capacity: None,
+            warn_silent: false,
+            panic_macro,
+        })
+    }
+
+    /// The `cfg` attribute to attach to generated stubs, or `None` if stubs should not be gated.
+    fn test_cfg(&self) -> Option<Attribute> {
+        if self.test_only {
+            None
+        } else {
+            let cfg = &self.cfg;
+            Some(syn::parse_quote!(#[cfg(#cfg)]))
+        }
+    }
+}
+
+/// The information needed to generate the stub for a single method.
+struct StubCtx<'a> {
+    config: &'a Config,
+    /// The name of the method being stubbed.
+    name: String,
+    /// The panicking expression for this method e.g. `todo!("<name>")`. This should only be used
+    /// via [StubCtx::todo].
+    todo_expr: proc_macro2::TokenStream,
+    /// Has the stub used `todo_expr` i.e. might it panic?
+    panics: Cell<bool>,
+    /// How many seeded literals have been generated for this method?
+    literals: Cell<u64>,
+    /// Generic type parameters (from either the trait or the method) which are bounded by
+    /// `Default`.
+    default_params: Vec<Ident>,
+    /// All generic type parameters (from either the trait or the method).
+    type_params: Vec<Ident>,
+}
+
+impl StubCtx<'_> {
+    /// Return the panicking expression for this method, recording that the stub might panic.
+    fn todo(&self) -> proc_macro2::TokenStream {
+        self.panics.set(true);
+        self.todo_expr.clone()
+    }
+
+    /// Return the stub for a path type `ty` which isn't special cased: `Default::default()` with
+    /// the `default` option (unless `ty` is a type parameter or an associated type, which might
+    /// not implement `Default`), or the panicking expression otherwise.
+    fn fallback(&self, ty: &TypePath) -> proc_macro2::TokenStream {
+        if self.config.default
+            && ty.qself.is_none()
+            && ty.path.segments[0].ident != "Self"
+            && !self.type_params.iter().any(|x| ty.path.is_ident(x))
+        {
+            quote! { ::core::default::Default::default() }
+        } else {
+            self.todo()
+        }
+    }
+}
+
+/// Return an iterator over the type parameters in `generics` which are bounded by `Default`,
+/// either inline (`<T: Default>`) or in a `where` clause (`where T: Default`).
+fn default_params(generics: &Generics) -> impl Iterator<Item = &Ident> {
+    let is_default = |b: &TypeParamBound| matches!(b, TypeParamBound::Trait(t) if t.path.segments.last().unwrap().ident == "Default");
+    generics
+        .type_params()
+        .filter(move |tp| {
+            tp.bounds.iter().any(is_default)
+                || generics.where_clause.iter().any(|w| {
+                    w.predicates.iter().any(|pred| {
+                        matches!(pred, WherePredicate::Type(pt)
+                            if matches!(&pt.bounded_ty, Type::Path(p) if p.path.is_ident(&tp.ident))
+                                && pt.bounds.iter().any(is_default))
+                    })
+                })
+        })
+        .map(|tp| &tp.ident)
 }
 
 /// Return `true` if this [WherePredicate] is `Self: Sized`.
@@ -161,7 +1203,17 @@ fn is_self_sized_pred(pred: &WherePredicate) -> bool {
     }
 }
 
-/// Recursively generate a stub expression for a type `ty` in method `name`. For example for:
+/// The iterator traits, all of which `std::iter::Empty` implements.
+const ITERATOR_TRAITS: &[&str] = &[
+    "IntoIterator",
+    "Iterator",
+    "DoubleEndedIterator",
+    "ExactSizeIterator",
+    "FusedIterator",
+];
+
+/// Recursively generate a stub expression for a type `ty` in the method described by `ctx`. For
+/// example for:
 /// ```text
 /// (u32, impl Iterator<...>, Option<impl Iterator<...>>)
 /// ```
@@ -174,50 +1226,685 @@ fn is_self_sized_pred(pred: &WherePredicate) -> bool {
 /// ```
 ///
 /// As that suggests, this method special cases certain types. When
-fn stub_expr_for_ty(ty: &Type, name: &str) -> proc_macro2::TokenStream {
+///
+/// All paths in the generated expression are fully qualified (e.g.
+/// `::std::collections::VecDeque::new()` rather than `VecDeque::new()`), so that stubs compile
+/// whatever is (or isn't) in scope where the trait is defined. Items that live in `core` are
+/// referenced through `::core` so that stubs which don't need `alloc` or `std` types also compile
+/// in `#![no_std]` crates.
+fn stub_expr_for_ty(ty: &Type, ctx: &StubCtx<'_>) -> proc_macro2::TokenStream {
     match ty {
         Type::ImplTrait(TypeImplTrait { bounds, .. }) => {
-            // Just `todo!()` for a type `impl X` doesn't work.
-            if bounds.iter().any(|x| {
-                matches!(x, TypeParamBound::Trait(t) if t.path.segments.last().unwrap().ident == "Iterator")
+            // Just `todo!()` for a type `impl X` doesn't work, so for some common traits we cast
+            // `todo!()` to a concrete type which implements them. The trait may be one of
+            // several bounds (e.g. `impl Error + Send + Sync + 'static`).
+            let has_bound = |ident: &str| trait_bound(bounds, ident).is_some();
+            // `Empty` implements all of the iterator traits, so any of them (e.g.
+            // `impl ExactSizeIterator + DoubleEndedIterator`) can be stubbed with it.
+            if let Some(output) = bounds.iter().find_map(|x| match x {
+                TypeParamBound::Trait(t) => assoc_type_arg(&t.path, "Future", "Output"),
+                _ => None,
+            }) {
+                // An `async` block implements `Future`, and its output is the stub for `Output`.
+                async_stub_expr(output, ctx)
+            } else if ITERATOR_TRAITS.iter().any(|x| has_bound(x)) {
+                match empty_iter_item(bounds) {
+                    // A concrete item type is enough for an empty iterator to satisfy inference.
+                    Some(item) if !contains_impl_trait(item) => {
+                        quote! { ::core::iter::empty::<#item>() }
+                    }
+                    _ => {
+                        let empty = empty_iter_ty(bounds)
+                            .unwrap_or_else(|| quote! { ::core::iter::Empty<_> });
+                        let todo = ctx.todo();
+                        quote! { #todo as #empty }
+                    }
+                }
+            } else if has_bound("Error") {
+                // `std::fmt::Error` is `Send + Sync + 'static`.
+                let todo = ctx.todo();
+                quote! { #todo as ::core::fmt::Error }
+            } else if let Some(convty) = bounds.iter().find_map(|x| match x {
+                TypeParamBound::Trait(t) => {
+                    first_type_arg(&t.path, "From").or_else(|| first_type_arg(&t.path, "Into"))
+                }
+                _ => None,
             }) {
-                quote! { todo!(#name) as std::iter::Empty<_> }
+                // `impl From<T>` and `impl Into<T>` are both satisfied by `T` itself. If there are
+                // other bounds, `T` might not satisfy them.
+                let todo = ctx.todo();
+                quote! { #todo as #convty }
+            } else if let Some(args) = fn_bound_args(bounds) {
+                closure_stub_expr(args, ctx)
+            } else if ctx.config.strict
+                && bounds.iter().any(|x| {
+                    matches!(x, TypeParamBound::Trait(t)
+                        if !AUTO_TRAITS.iter().any(|a| t.path.segments.last().unwrap().ident == a))
+                })
+            {
+                syn::Error::new_spanned(
+                    ty,
+                    format!(
+                        "`test_stubs` can't stub this `impl Trait` returned by `{}`: use \
+                         `#[stub_with]` or `#[no_stub]`",
+                        ctx.name
+                    ),
+                )
+                .to_compile_error()
             } else {
                 // What can we do for arbitrary `impl` types? Just outputting `todo!()` is unlikely
                 // to satisfy type inference.
-                quote! { todo!(#name) }
+                ctx.todo()
             }
         }
+        // A generic type parameter bounded by `Default` (e.g. `fn f<T: Default>() -> T`) can be
+        // constructed directly. Other bounds (e.g. `T: From<X>`) fall through to `todo!()`.
+        Type::Path(ty_p)
+            if ty_p.qself.is_none() && ctx.default_params.iter().any(|x| ty_p.path.is_ident(x)) =>
+        {
+            quote! { ::core::default::Default::default() }
+        }
+        // User-registered types are matched on the last segment of their path, so `MyEnum` and
+        // `crate::MyEnum` are both stubbed with the registered expression.
+        Type::Path(ty_p)
+            if ty_p.qself.is_none()
+                && let Some((_, expr)) = ctx
+                    .config
+                    .enum_defaults
+                    .iter()
+                    .find(|(x, _)| ty_p.path.segments.last().unwrap().ident == *x) =>
+        {
+            quote! { #expr }
+        }
+        Type::Path(ty_p)
+            if let Some(seed) = ctx.config.seed
+                && let Some(lit) = seeded_literal(ty_p, seed, ctx) =>
+        {
+            lit
+        }
+        Type::Path(_) if ctx.config.uninit && is_numeric_prim(ty) => {
+            quote! { unsafe { ::core::mem::MaybeUninit::uninit().assume_init() } }
+        }
         Type::Path(ty_p) => {
             let last = ty_p.path.segments.last().unwrap();
             match &last.arguments {
                 PathArguments::AngleBracketed(args) => {
-                    let outerty = args
-                        .args
-                        .iter()
-                        .find_map(|arg| match arg {
-                            GenericArgument::Type(ty) => Some(ty),
-                            _ => None,
-                        })
-                        .unwrap();
-                    let stub = stub_expr_for_ty(outerty, name);
+                    // Types with only lifetime or const arguments (e.g. `Arguments<'a>`) can't be
+                    // special cased.
+                    let Some(outerty) = nth_type_arg(args, 0) else {
+                        return ctx.fallback(ty_p);
+                    };
                     // We special case certain common types where we are easily able to create
                     // expressions / variants that, even with deeply nested types, will satisfy
                     // type inference.
                     match last.ident.to_string().as_str() {
-                        "Box" => quote! { Box::new(#stub) },
-                        "Option" => quote! { Some(#stub) },
-                        "Result" => quote! { Ok(#stub) },
-                        _ => quote! { todo!(#name) },
+                        // `str` can't be unsized through `new`, so is created with `from`.
+                        "Box" | "Arc" | "Rc" => {
+                            let ptr = match last.ident.to_string().as_str() {
+                                "Box" => quote! { ::std::boxed::Box },
+                                "Arc" => quote! { ::std::sync::Arc },
+                                _ => quote! { ::std::rc::Rc },
+                            };
+                            match outerty {
+                                Type::Path(p) if p.path.is_ident("str") => {
+                                    quote! { #ptr::from("") }
+                                }
+                                _ => match boxed_stub_expr(outerty, ctx) {
+                                    Some(stub) => quote! { #ptr::new(#stub) },
+                                    None => ctx.todo(),
+                                },
+                            }
+                        }
+                        // `cow_default` only applies to `Cow`s which aren't special cased.
+                        "Cow" => match (outerty, &ctx.config.cow_default) {
+                            (Type::Path(p), _) if p.path.is_ident("str") => {
+                                quote! { ::std::borrow::Cow::Borrowed("") }
+                            }
+                            (Type::Slice(_), _) => quote! { ::std::borrow::Cow::Borrowed(&[]) },
+                            (_, Some(x)) => quote! { #x },
+                            (_, None) => ctx.todo(),
+                        },
+                        "Pin" => {
+                            if let Type::Path(p) = outerty
+                                && let Some(boxedty) = first_type_arg(&p.path, "Box")
+                                && let Some(stub) = boxed_stub_expr(boxedty, ctx)
+                            {
+                                quote! { ::std::boxed::Box::pin(#stub) }
+                            } else {
+                                ctx.todo()
+                            }
+                        }
+                        "Option" => {
+                            let stub = stub_expr_for_ty(outerty, ctx);
+                            quote! { ::core::option::Option::Some(#stub) }
+                        }
+                        // An uninhabited error type can't be constructed, so `Ok` is the only
+                        // possible stub.
+                        "Result"
+                            if ctx.config.result_err
+                                && let Some(errty) = nth_type_arg(args, 1)
+                                && is_uninhabited(errty) =>
+                        {
+                            syn::Error::new_spanned(
+                                errty,
+                                "`result = \"err\"` can't be used with an uninhabited error type",
+                            )
+                            .to_compile_error()
+                        }
+                        // The error type must implement `Default`, unless it's an `impl Trait`,
+                        // which has no `Default` to call: that gets the type's normal stub.
+                        "Result" if ctx.config.result_err => {
+                            let err = match nth_type_arg(args, 1) {
+                                Some(errty) if contains_impl_trait(errty) => {
+                                    let stub = stub_expr_for_ty(errty, ctx);
+                                    quote! { ::core::result::Result::Err(#stub) }
+                                }
+                                _ => quote! {
+                                    ::core::result::Result::Err(::core::default::Default::default())
+                                },
+                            };
+                            if contains_impl_trait(outerty) {
+                                // An `impl Trait` in the `Ok` type can't be inferred from `Err`,
+                                // so we also generate an (unreachable) `Ok` stub. Since it never
+                                // runs, it doesn't count towards whether this stub panics.
+                                let panics = ctx.panics.get();
+                                let stub = stub_expr_for_ty(outerty, ctx);
+                                ctx.panics.set(panics);
+                                quote! {
+                                    if true { #err } else { ::core::result::Result::Ok(#stub) }
+                                }
+                            } else {
+                                err
+                            }
+                        }
+                        "Result" => {
+                            let stub = stub_expr_for_ty(outerty, ctx);
+                            let ok = quote! { ::core::result::Result::Ok(#stub) };
+                            // Similarly, an `impl Trait` in the `Err` type can't be inferred from
+                            // `Ok`, so we also generate an (unreachable) `Err` stub.
+                            match nth_type_arg(args, 1) {
+                                Some(errty) if contains_impl_trait(errty) => {
+                                    let panics = ctx.panics.get();
+                                    let stub = stub_expr_for_ty(errty, ctx);
+                                    ctx.panics.set(panics);
+                                    quote! {
+                                        if true { #ok } else { ::core::result::Result::Err(#stub) }
+                                    }
+                                }
+                                _ => ok,
+                            }
+                        }
+                        // STUB: the `MaybeUninit` is uninitialized. Constructing it is safe: it
+                        // is up to the caller not to `assume_init` it.
+                        "MaybeUninit" => quote! { ::core::mem::MaybeUninit::uninit() },
+                        // `std::rc::Weak` and `std::sync::Weak` can't be told apart from an
+                        // unqualified path, but both implement `Default` as `Weak::new()`.
+                        "Weak" => quote! { ::core::default::Default::default() },
+                        // Since its start is greater than its end, this range is empty. We avoid
+                        // `1..=0`, which clippy denies by default.
+                        "RangeInclusive" if is_integer_prim(outerty) => {
+                            quote! { ::core::ops::RangeInclusive::new(1, 0) }
+                        }
+                        // The integer type is inferred from the return type.
+                        "NonZero" if is_integer_prim(outerty) => {
+                            quote! { ::core::num::NonZero::new(1).unwrap() }
+                        }
+                        "OnceCell" => quote! { ::core::cell::OnceCell::new() },
+                        "OnceLock" => quote! { ::std::sync::OnceLock::new() },
+                        "Vec" if let Some(cap) = ctx.config.vec_capacity => {
+                            let cap = Literal::usize_unsuffixed(cap);
+                            quote! { ::std::vec::Vec::with_capacity(#cap) }
+                        }
+                        // `FromIterator` is implemented for `HashMap`s with any hasher that
+                        // implements `Default`.
+                        "Vec" | "VecDeque" | "LinkedList" | "HashMap" | "HashSet" | "BTreeMap"
+                        | "BTreeSet"
+                            if ctx.config.collections_collect =>
+                        {
+                            quote! { ::core::iter::Iterator::collect(::core::iter::empty()) }
+                        }
+                        "Vec" => quote! { ::std::vec::Vec::new() },
+                        "VecDeque" => quote! { ::std::collections::VecDeque::new() },
+                        "LinkedList" => quote! { ::std::collections::LinkedList::new() },
+                        // `HashMap::new` and `HashSet::new` only exist for the default hasher. Other
+                        // hashers must implement `Default`.
+                        "HashMap" if nth_type_arg(args, 2).is_none_or(is_random_state) => {
+                            quote! { ::std::collections::HashMap::new() }
+                        }
+                        "HashMap" => quote! { ::std::collections::HashMap::default() },
+                        "HashSet" if nth_type_arg(args, 1).is_none_or(is_random_state) => {
+                            quote! { ::std::collections::HashSet::new() }
+                        }
+                        "HashSet" => quote! { ::std::collections::HashSet::default() },
+                        "BTreeMap" => quote! { ::std::collections::BTreeMap::new() },
+                        "BTreeSet" => quote! { ::std::collections::BTreeSet::new() },
+                        "PhantomData" => quote! { ::core::marker::PhantomData },
+                        "BuildHasherDefault" => quote! { ::core::default::Default::default() },
+                        _ => ctx.fallback(ty_p),
                     }
                 }
-                _ => quote! { todo!(#name) },
+                PathArguments::None => match last.ident.to_string().as_str() {
+                    "PhantomPinned" => quote! { ::core::marker::PhantomPinned },
+                    "String" => quote! { ::std::string::String::new() },
+                    "OsString" => quote! { ::std::ffi::OsString::new() },
+                    "PathBuf" => quote! { ::std::path::PathBuf::new() },
+                    // `CString::new` takes the string's contents, and returns a `Result`.
+                    "CString" => quote! { ::std::ffi::CString::default() },
+                    // Standard library types that implement `Default` but which aren't otherwise
+                    // special cased.
+                    "DefaultHasher" | "Duration" | "RandomState" => {
+                        quote! { ::core::default::Default::default() }
+                    }
+                    "Error" if ty_p.path.segments.iter().any(|x| x.ident == "fmt") => {
+                        quote! { ::core::fmt::Error }
+                    }
+                    // The aliases of `NonZero<T>` e.g. `NonZeroU32`.
+                    x if let Some(prim) = x.strip_prefix("NonZero")
+                        && syn::parse_str(&prim.to_lowercase())
+                            .is_ok_and(|x| is_integer_prim(&x)) =>
+                    {
+                        quote! { ::core::num::#last::new(1).unwrap() }
+                    }
+                    _ => ctx.fallback(ty_p),
+                },
+                _ => ctx.todo(),
+            }
+        }
+        // We never generate `unsafe` code without the user asking for it, so types whose values
+        // might require `unsafe` to construct (raw pointers, or `NonNull<T>` above) are stubbed
+        // with `todo!()`.
+        Type::Ptr(_) => ctx.todo(),
+        // An empty slice can be borrowed for any lifetime, whatever its element type.
+        Type::Reference(x) if x.mutability.is_none() && matches!(*x.elem, Type::Slice(_)) => {
+            match &ctx.config.slice_default {
+                Some(x) => quote! { #x },
+                None => quote! { &[] },
             }
         }
+        // Similarly, `""` is a `&'static str`, so it satisfies `&str` with any lifetime.
+        Type::Reference(x)
+            if x.mutability.is_none()
+                && matches!(&*x.elem, Type::Path(p) if p.qself.is_none() && p.path.is_ident("str")) =>
+        {
+            quote! { "" }
+        }
         Type::Tuple(x) => {
-            let elems: Vec<_> = x.elems.iter().map(|x| stub_expr_for_ty(x, name)).collect();
+            let elems: Vec<_> = x.elems.iter().map(|x| stub_expr_for_ty(x, ctx)).collect();
             quote! { (#(#elems),*) }
         }
-        _ => quote! { todo!(#name) },
+        _ => ctx.todo(),
+    }
+}
+
+/// Convert `ident` (assumed to be in `UpperCamelCase`) into `UPPER_SNAKE_CASE`.
+fn upper_snake_case(ident: &Ident) -> String {
+    let chars = ident.unraw().to_string().chars().collect::<Vec<_>>();
+    let mut out = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if i > 0
+            && c.is_uppercase()
+            && (!chars[i - 1].is_uppercase() || chars.get(i + 1).is_some_and(|x| x.is_lowercase()))
+        {
+            out.push('_');
+        }
+        out.extend(c.to_uppercase());
+    }
+    out
+}
+
+/// If `ty_p` is a primitive numeric or `bool` type, return a pseudo-random literal of that type.
+/// The literal is deterministically derived from `seed`, the method's name, and the number of
+/// literals previously generated for the method.
+fn seeded_literal(
+    ty_p: &TypePath,
+    seed: u64,
+    ctx: &StubCtx<'_>,
+) -> Option<proc_macro2::TokenStream> {
+    if ty_p.qself.is_some() {
+        return None;
+    }
+    let ident = ty_p.path.get_ident()?.to_string();
+    // Hash the method name with FNV-1a, then mix in the seed and literal count with splitmix64.
+    let mut x = ctx.name.bytes().fold(0xcbf29ce484222325u64, |h, b| {
+        (h ^ u64::from(b)).wrapping_mul(0x100000001b3)
+    });
+    let n = ctx.literals.get();
+    ctx.literals.set(n + 1);
+    x ^= seed.wrapping_add(n.wrapping_mul(0x9e3779b97f4a7c15));
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^= x >> 31;
+    // Signed integers are given non-negative values, since a negative literal isn't a single token.
+    let lit = match ident.as_str() {
+        "bool" => {
+            let b = x & 1 == 1;
+            return Some(quote! { #b });
+        }
+        "i8" => Literal::i8_suffixed((x as i8) & i8::MAX),
+        "i16" => Literal::i16_suffixed((x as i16) & i16::MAX),
+        "i32" => Literal::i32_suffixed((x as i32) & i32::MAX),
+        "i64" => Literal::i64_suffixed((x as i64) & i64::MAX),
+        "i128" => Literal::i128_suffixed(i128::from((x as i64) & i64::MAX)),
+        "isize" => Literal::isize_suffixed((x as isize) & isize::MAX),
+        "u8" => Literal::u8_suffixed(x as u8),
+        "u16" => Literal::u16_suffixed(x as u16),
+        "u32" => Literal::u32_suffixed(x as u32),
+        "u64" => Literal::u64_suffixed(x),
+        "u128" => Literal::u128_suffixed(u128::from(x)),
+        "usize" => Literal::usize_suffixed(x as usize),
+        "f32" => Literal::f32_suffixed((x % 10000) as f32 / 10.0),
+        "f64" => Literal::f64_suffixed((x % 10000) as f64 / 10.0),
+        _ => return None,
+    };
+    Some(quote! { #lit })
+}
+
+/// Auto traits, which `todo!()` satisfies whatever else it is used for.
+const AUTO_TRAITS: &[&str] = &["Send", "Sync", "Unpin", "UnwindSafe", "RefUnwindSafe"];
+
+/// If `bounds` include an iterator trait with an `Item` type, return that type.
+fn empty_iter_item(bounds: &Punctuated<TypeParamBound, Token![+]>) -> Option<&Type> {
+    bounds.iter().find_map(|x| match x {
+        TypeParamBound::Trait(t) => ITERATOR_TRAITS
+            .iter()
+            .find_map(|i| assoc_type_arg(&t.path, i, "Item")),
+        _ => None,
+    })
+}
+
+/// If `bounds` include an iterator trait with an `Item` type, return the `std::iter::Empty<T>` type
+/// which satisfies them. Giving the item type explicitly helps inference in generic contexts, but
+/// `impl Trait`s can't be written in an expression, so an `impl Iterator` item is itself replaced
+/// with `std::iter::Empty<T>` (e.g. `impl Iterator<Item = impl Iterator<Item = u8>>` becomes
+/// `Empty<Empty<u8>>`). Returns `None` if there is no `Item` type or it contains another `impl
+/// Trait`.
+fn empty_iter_ty(
+    bounds: &Punctuated<TypeParamBound, Token![+]>,
+) -> Option<proc_macro2::TokenStream> {
+    let item = empty_iter_item(bounds)?;
+    let item = match item {
+        Type::ImplTrait(x) => empty_iter_ty(&x.bounds)?,
+        _ if contains_impl_trait(item) => return None,
+        _ => quote! { #item },
+    };
+    Some(quote! { ::core::iter::Empty<#item> })
+}
+
+/// Does `ty` contain an `impl Trait` anywhere within it?
+fn contains_impl_trait(ty: &Type) -> bool {
+    fn walk(toks: proc_macro2::TokenStream) -> bool {
+        toks.into_iter().any(|x| match x {
+            TokenTree::Ident(x) => x == "impl",
+            TokenTree::Group(x) => walk(x.stream()),
+            _ => false,
+        })
+    }
+    walk(ty.to_token_stream())
+}
+
+/// Does `ty` contain a reference without a lifetime (e.g. `&str`), a `'_` lifetime, or a `Cow`
+/// without a lifetime (e.g. `Cow<str>`) anywhere within it? Other types whose lifetimes are hidden
+/// (e.g. `MyRef<u8>` for `struct MyRef<'a, T>`) can't be recognised syntactically.
+fn contains_elided_lifetime(ty: &Type) -> bool {
+    fn walk(toks: proc_macro2::TokenStream) -> bool {
+        let mut toks = toks.into_iter().peekable();
+        while let Some(x) = toks.next() {
+            let elided = match x {
+                TokenTree::Punct(p) if p.as_char() == '&' => {
+                    !matches!(toks.peek(), Some(TokenTree::Punct(p)) if p.as_char() == '\'')
+                }
+                TokenTree::Punct(p) if p.as_char() == '\'' => {
+                    matches!(toks.peek(), Some(TokenTree::Ident(x)) if x == "_")
+                }
+                TokenTree::Ident(x) if x == "Cow" => {
+                    let mut args = toks.clone().skip(1);
+                    !matches!(args.next(), Some(TokenTree::Punct(p)) if p.as_char() == '\'')
+                }
+                TokenTree::Group(x) => walk(x.stream()),
+                _ => false,
+            };
+            if elided {
+                return true;
+            }
+        }
+        false
+    }
+    walk(ty.to_token_stream())
+}
+
+/// Is `ty` one of Rust's primitive integer or float types?
+fn is_numeric_prim(ty: &Type) -> bool {
+    is_integer_prim(ty)
+        || matches!(ty, Type::Path(p) if p.qself.is_none()
+        && (p.path.is_ident("f32") || p.path.is_ident("f64")))
+}
+
+/// Is `ty` one of Rust's primitive integer types?
+fn is_integer_prim(ty: &Type) -> bool {
+    const INTEGER_PRIMS: &[&str] = &[
+        "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize",
+    ];
+    matches!(ty, Type::Path(p) if p.qself.is_none()
+        && INTEGER_PRIMS.iter().any(|x| p.path.is_ident(x)))
+}
+
+/// Generate an `async` block whose output is the stub for `output`. If `output` is `()`, the block
+/// is empty, since clippy warns about `async { () }`. Otherwise the block is `move`, since the stub
+/// may refer to the method's arguments (see `debug_args`) and the future can outlive the call.
+fn async_stub_expr(output: &Type, ctx: &StubCtx<'_>) -> proc_macro2::TokenStream {
+    match output {
+        Type::Tuple(x) if x.elems.is_empty() => quote! { async {} },
+        _ => {
+            let stub = stub_expr_for_ty(output, ctx);
+            quote! { async move { #stub } }
+        }
+    }
+}
+
+/// If one of `bounds` is `Fn`, `FnMut`, or `FnOnce` (e.g. `Fn(u8) -> u8`), return its arguments.
+fn fn_bound_args(
+    bounds: &Punctuated<TypeParamBound, Token![+]>,
+) -> Option<&ParenthesizedGenericArguments> {
+    bounds.iter().find_map(|b| match b {
+        TypeParamBound::Trait(t)
+            if let Some(last) = t.path.segments.last()
+                && ["Fn", "FnMut", "FnOnce"].iter().any(|x| last.ident == x) =>
+        {
+            match &last.arguments {
+                PathArguments::Parenthesized(args) => Some(args),
+                _ => None,
+            }
+        }
+        _ => None,
+    })
+}
+
+/// Generate a closure which takes (and ignores) `args`' inputs and whose body is the stub for
+/// `args`' output, e.g. for `Fn(u8) -> T` it generates `move |_: u8| -> T { <stub for T> }`. A
+/// closure implements `FnOnce`, and `FnMut` and `Fn` if its body allows, so the same stub works
+/// for all three. The closure is `move` as the stub may refer to the method's arguments (see
+/// `debug_args`) and the closure can outlive the method call.
+fn closure_stub_expr(
+    args: &ParenthesizedGenericArguments,
+    ctx: &StubCtx<'_>,
+) -> proc_macro2::TokenStream {
+    let inputs = args.inputs.iter();
+    let (output, body) = match &args.output {
+        ReturnType::Default => (quote!(), quote!()),
+        ReturnType::Type(arrow, ty) => (quote! { #arrow #ty }, stub_expr_for_ty(ty, ctx)),
+    };
+    quote! { move |#(_: #inputs),*| #output { #body } }
+}
+
+/// Generate a stub expression for the contents of a `Box` (or similar pointer) of type `ty`. Since
+/// the contents need not be `Sized`, this handles some unsized types: e.g. for `[T]` it generates
+/// `[]`, for `dyn Any` it generates `()`, for `dyn Iterator<Item = T>` it generates
+/// `std::iter::empty::<T>()`, for `dyn Future<Output = T>` it generates
+/// `async move { <stub for T> }`, and for `dyn FnMut(u8) -> T` it generates
+/// `move |_: u8| -> T { <stub for T> }`, relying on an unsizing coercion. Returns `None` for other
+/// trait objects: `Box::new(todo!())` doesn't compile for them (since `!` doesn't implement an
+/// arbitrary trait), so the whole pointer must be stubbed with `todo!()` instead.
+fn boxed_stub_expr(ty: &Type, ctx: &StubCtx<'_>) -> Option<proc_macro2::TokenStream> {
+    let stub = match ty {
+        Type::Slice(_) => quote! { [] },
+        // The closure's types must be explicit, since they can't be inferred through the unsizing
+        // coercion.
+        Type::TraitObject(x) if let Some(args) = fn_bound_args(&x.bounds) => {
+            closure_stub_expr(args, ctx)
+        }
+        // As with `impl Future`, an `async` block implements `dyn Future`.
+        Type::TraitObject(x)
+            if let Some(output) = x.bounds.iter().find_map(|b| match b {
+                TypeParamBound::Trait(t) => assoc_type_arg(&t.path, "Future", "Output"),
+                _ => None,
+            }) =>
+        {
+            async_stub_expr(output, ctx)
+        }
+        // `()` is `Send + Sync + 'static`, so it satisfies `dyn Any` with any auto-trait bounds.
+        Type::TraitObject(x) if trait_bound(&x.bounds, "Any").is_some() => quote! { () },
+        // As with `impl Error`, `std::fmt::Error` is `Send + Sync + 'static`.
+        Type::TraitObject(x) if trait_bound(&x.bounds, "Error").is_some() => {
+            quote! { ::core::fmt::Error }
+        }
+        // The item type can't be inferred through the unsizing coercion, so it must be explicit.
+        Type::TraitObject(x)
+            if ITERATOR_TRAITS
+                .iter()
+                .any(|y| trait_bound(&x.bounds, y).is_some()) =>
+        {
+            match empty_iter_item(&x.bounds) {
+                Some(item) => quote! { ::core::iter::empty::<#item>() },
+                None => quote! { ::core::iter::empty() },
+            }
+        }
+        Type::TraitObject(_) => return None,
+        _ => stub_expr_for_ty(ty, ctx),
+    };
+    Some(stub)
+}
+
+/// Return the trait bound in `bounds` whose last path segment is `ident` (e.g. for `ident ==
+/// "Any"`, `dyn Any + Send` or `dyn std::any::Any`), if there is one.
+fn trait_bound<'a>(
+    bounds: &'a Punctuated<TypeParamBound, Token![+]>,
+    ident: &str,
+) -> Option<&'a TypeParamBound> {
+    bounds.iter().find(
+        |x| matches!(x, TypeParamBound::Trait(t) if t.path.segments.last().unwrap().ident == ident),
+    )
+}
+
+/// Return the `n`th type argument (skipping lifetimes and consts) in `args`.
+fn nth_type_arg(args: &AngleBracketedGenericArguments, n: usize) -> Option<&Type> {
+    args.args
+        .iter()
+        .filter_map(|arg| match arg {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        })
+        .nth(n)
+}
+
+/// Is `ty` `RandomState`, the default hasher for `HashMap` and `HashSet`?
+fn is_random_state(ty: &Type) -> bool {
+    matches!(ty, Type::Path(p) if p.qself.is_none()
+        && p.path.segments.last().unwrap().ident == "RandomState")
+}
+
+/// Is `ty` `!` or `Infallible`, neither of which has any values?
+fn is_uninhabited(ty: &Type) -> bool {
+    match ty {
+        Type::Never(_) => true,
+        Type::Path(p) => p.qself.is_none() && p.path.segments.last().unwrap().ident == "Infallible",
+        _ => false,
+    }
+}
+
+/// If `path`'s last segment is `ident` (e.g. for `ident == "Future"`, `Future<Output = T>`), return
+/// the type of its associated type binding `assoc` (e.g. for `assoc == "Output"`, `T`).
+fn assoc_type_arg<'a>(path: &'a Path, ident: &str, assoc: &str) -> Option<&'a Type> {
+    if let Some(last) = path.segments.last()
+        && last.ident == ident
+        && let PathArguments::AngleBracketed(args) = &last.arguments
+    {
+        args.args.iter().find_map(|arg| match arg {
+            GenericArgument::AssocType(x) if x.ident == assoc => Some(&x.ty),
+            _ => None,
+        })
+    } else {
+        None
+    }
+}
+
+/// If `path`'s last segment is `ident` (e.g. for `ident == "Box"`, `Box<T>` or
+/// `std::boxed::Box<T>`), return its first type argument (skipping lifetimes).
+fn first_type_arg<'a>(path: &'a Path, ident: &str) -> Option<&'a Type> {
+    if let Some(last) = path.segments.last()
+        && last.ident == ident
+        && let PathArguments::AngleBracketed(args) = &last.arguments
+    {
+        nth_type_arg(args, 0)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    /// Expand `#[test_stubs(<attr>)]` on `trait_item`, returning the output as a string.
+    fn expand_trait(attr: proc_macro2::TokenStream, trait_item: ItemTrait) -> String {
+        stub_trait(&parse_config(attr).unwrap(), trait_item).to_string()
+    }
+
+    /// Expand `#[test_stubs(<attr>)]` on `impl_item`, returning the output as a string.
+    fn expand_impl(attr: proc_macro2::TokenStream, impl_item: ItemImpl) -> String {
+        stub_impl(&parse_config(attr).unwrap(), impl_item).to_string()
+    }
+
+    #[test]
+    fn result_err_uninhabited() {
+        let out = expand_trait(
+            quote! { result = "err", test_only },
+            parse_quote! {
+                trait T {
+                    fn f(&self) -> Result<u8, std::convert::Infallible>;
+                }
+            },
+        );
+        assert!(out.contains(r#"`result = \"err\"` can't be used with an uninhabited error type"#));
+    }
+
+    #[test]
+    fn strict() {
+        let out = expand_trait(
+            quote! { strict, test_only },
+            parse_quote! {
+                trait T {
+                    fn f(&self) -> impl std::fmt::Display;
+                }
+            },
+        );
+        assert!(out.contains(
+            "`test_stubs` can't stub this `impl Trait` returned by `f`: use `#[stub_with]` or \
+             `#[no_stub]`"
+        ));
+    }
+
+    #[test]
+    fn no_stub_without_body() {
+        let out = expand_impl(
+            quote! { test_only },
+            parse_quote! {
+                impl T for S {
+                    #[no_stub]
+                    fn f(&self) -> u8;
+                }
+            },
+        );
+        assert!(out.contains("a method without a body can't be skipped: implement it instead"));
     }
 }
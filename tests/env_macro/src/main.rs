@@ -0,0 +1,16 @@
+//! Prints the panic message of a stub. `tests/test_stubs.rs` runs this with `TEST_STUBS_MACRO` set.
+use test_stubs::test_stubs;
+
+#[test_stubs(test_only)]
+trait T {
+    fn x(&self);
+}
+
+struct S;
+impl T for S {}
+
+fn main() {
+    std::panic::set_hook(Box::new(|_| ()));
+    let e = std::panic::catch_unwind(|| S.x()).unwrap_err();
+    print!("{}", e.downcast_ref::<&str>().unwrap());
+}
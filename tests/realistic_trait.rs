@@ -0,0 +1,129 @@
+use std::{
+    future::Future,
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+use test_stubs::test_stubs;
+
+#[derive(Clone, Debug, PartialEq)]
+struct Row {
+    id: u64,
+    name: String,
+}
+
+#[derive(Debug, PartialEq)]
+struct DbError(String);
+
+// A trait resembling a database connection: each test implements only the methods it needs.
+#[test_stubs]
+trait Connection {
+    fn rows(&self) -> Vec<Row>;
+    fn find(&self, id: u64) -> Option<Row>;
+    fn execute(&mut self, sql: &str) -> Result<u64, DbError>;
+    fn scan(&self) -> impl Iterator<Item = Row>;
+    async fn query(&self, sql: &str) -> Result<Vec<Row>, DbError>;
+    fn close(self);
+
+    fn count(&self) -> usize {
+        self.scan().count()
+    }
+}
+
+/// Run `f` to completion. The futures in these tests never wait, so we don't need a real executor.
+fn block_on<F: Future>(f: F) -> F::Output {
+    let mut f = pin!(f);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(x) = f.as_mut().poll(&mut cx) {
+            return x;
+        }
+    }
+}
+
+fn row(id: u64) -> Row {
+    Row {
+        id,
+        name: format!("row{id}"),
+    }
+}
+
+#[test]
+fn find() {
+    struct Conn;
+    impl Connection for Conn {
+        fn find(&self, id: u64) -> Option<Row> {
+            (id == 1).then(|| row(1))
+        }
+    }
+
+    let c = Conn;
+    assert_eq!(c.find(1), Some(row(1)));
+    assert_eq!(c.find(2), None);
+    // Collection stubs are empty rather than panicking.
+    assert!(c.rows().is_empty());
+    assert!(std::panic::catch_unwind(|| Conn.execute("")).is_err());
+}
+
+#[test]
+fn execute() {
+    struct Conn(Vec<String>);
+    impl Connection for Conn {
+        fn execute(&mut self, sql: &str) -> Result<u64, DbError> {
+            if sql.is_empty() {
+                return Err(DbError("empty statement".to_owned()));
+            }
+            self.0.push(sql.to_owned());
+            Ok(1)
+        }
+    }
+
+    let mut c = Conn(Vec::new());
+    assert_eq!(c.execute("DELETE FROM t"), Ok(1));
+    assert_eq!(c.execute(""), Err(DbError("empty statement".to_owned())));
+    assert_eq!(c.0, ["DELETE FROM t"]);
+}
+
+#[test]
+fn scan() {
+    struct Conn;
+    impl Connection for Conn {
+        fn scan(&self) -> impl Iterator<Item = Row> {
+            (0..3).map(row)
+        }
+    }
+
+    // A provided method which relies on an overridden method.
+    assert_eq!(Conn.count(), 3);
+    assert!(std::panic::catch_unwind(|| Conn.close()).is_err());
+}
+
+#[test]
+fn query() {
+    struct Conn;
+    impl Connection for Conn {
+        async fn query(&self, sql: &str) -> Result<Vec<Row>, DbError> {
+            Ok(vec![Row {
+                id: 0,
+                name: sql.to_owned(),
+            }])
+        }
+    }
+
+    assert_eq!(
+        block_on(Conn.query("SELECT 1")),
+        Ok(vec![Row {
+            id: 0,
+            name: "SELECT 1".to_owned()
+        }])
+    );
+    // The `scan` stub is an empty iterator.
+    assert_eq!(Conn.count(), 0);
+}
+
+#[test]
+fn query_stub() {
+    struct Conn;
+    impl Connection for Conn {}
+
+    assert_eq!(block_on(Conn.query("SELECT 1")), Ok(Vec::new()));
+}
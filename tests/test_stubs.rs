@@ -30,6 +30,82 @@ fn name_of_fn() {
     S.x();
 }
 
+// `#[no_stub]`
+#[test_stubs]
+trait NoStubT {
+    #[no_stub]
+    fn new() -> Self;
+    fn x(&self) -> u8;
+}
+
+#[test_stubs]
+trait SkipT {
+    #[test_stub(skip)]
+    fn required(&self) -> u8;
+    fn y(&self) -> u8;
+}
+
+#[test]
+fn no_stubt() {
+    struct S;
+    impl NoStubT for S {
+        fn new() -> Self {
+            S
+        }
+    }
+
+    assert!(std::panic::catch_unwind(|| S::new().x()).is_err());
+
+    impl SkipT for S {
+        fn required(&self) -> u8 {
+            1
+        }
+    }
+    assert_eq!(S.required(), 1);
+    assert!(std::panic::catch_unwind(|| S.y()).is_err());
+}
+
+// `#[stub_with]`
+#[test_stubs]
+trait StubWithT {
+    #[stub_with = "Doc(7)"]
+    fn doc(&self) -> Doc;
+    #[stub_with = "[a, b].into_iter()"]
+    fn iter(&self, a: u8, b: u8) -> impl Iterator<Item = u8>;
+    #[test_stub(expr = Doc::dummy())]
+    fn doc2(&self) -> Doc;
+    // An explicit expression takes precedence over the usual stub for the return type.
+    #[test_stub(expr = vec![1, 2])]
+    fn vec(&self) -> Vec<u8>;
+}
+
+impl Doc {
+    fn dummy() -> Self {
+        Doc(9)
+    }
+}
+
+trait StubWithImplT {
+    fn doc(&self) -> Doc;
+}
+
+#[test]
+fn stub_witht() {
+    struct S;
+    impl StubWithT for S {}
+    #[test_stubs]
+    impl StubWithImplT for S {
+        #[stub_with = "Doc(3)"]
+        fn doc(&self) -> Doc;
+    }
+
+    assert_eq!(StubWithT::doc(&S), Doc(7));
+    assert_eq!(S.iter(1, 2).collect::<Vec<_>>(), [1, 2]);
+    assert_eq!(S.doc2(), Doc(9));
+    assert_eq!(S.vec(), [1, 2]);
+    assert_eq!(StubWithImplT::doc(&S), Doc(3));
+}
+
 // `impl Iterator`
 #[test_stubs]
 trait IterT {
@@ -59,6 +135,160 @@ fn itert() {
     assert_eq!(s.opt_iter2().unwrap().collect::<Vec<_>>().as_slice(), &[2]);
 }
 
+// User-supplied `allow`s are kept alongside the ones `test_stubs` adds.
+#[deny(warnings)]
+#[test_stubs]
+trait AllowIterT {
+    #[allow(dead_code, unreachable_code)]
+    fn iter(&self, x: u8) -> impl Iterator<Item = u8>;
+}
+
+#[test]
+fn allow_itert() {
+    struct S;
+    impl AllowIterT for S {}
+
+    assert_eq!(S.iter(1).count(), 0);
+}
+
+// `impl Iterator` whose item is an associated type
+#[test_stubs]
+trait AssocIterT {
+    type Item;
+    fn iter(&self) -> impl Iterator<Item = Self::Item>;
+}
+
+#[test]
+fn assoc_itert() {
+    struct S;
+    impl AssocIterT for S {
+        type Item = u8;
+    }
+
+    assert_eq!(S.iter().count(), 0);
+}
+
+// `cfg_attr`s are kept on both variants, whether or not their condition overlaps with `test`.
+#[deny(warnings)]
+#[test_stubs]
+trait CfgAttrT {
+    #[cfg_attr(test, must_use)]
+    #[cfg_attr(not(test), doc = "Not a test.")]
+    #[cfg_attr(any(), inline)]
+    fn x(&self) -> u8;
+}
+
+#[test]
+fn cfg_attrt() {
+    struct S;
+    impl CfgAttrT for S {}
+
+    assert!(std::panic::catch_unwind(|| S.x()).is_err());
+}
+
+// Doc attributes are kept on both variants: if `#[doc(hidden)]` was dropped, `missing_docs` would
+// complain about `hidden`.
+#[deny(missing_docs)]
+#[test_stubs]
+/// A trait.
+pub trait DocHiddenT {
+    #[doc(hidden)]
+    fn hidden(&self) -> u8;
+    /// Documented.
+    #[doc(alias = "documented_alias")]
+    fn documented(&self) -> u8;
+}
+
+#[test]
+fn doc_hiddent() {
+    struct S;
+    impl DocHiddenT for S {}
+
+    assert!(std::panic::catch_unwind(|| S.hidden()).is_err());
+    assert!(std::panic::catch_unwind(|| S.documented()).is_err());
+}
+
+// Iterator traits other than `Iterator`
+#[test_stubs]
+trait IterFamilyT {
+    fn exact(&self) -> impl ExactSizeIterator + DoubleEndedIterator<Item = u8>;
+    fn fused(&self) -> impl std::iter::FusedIterator<Item = u8>;
+}
+
+#[test]
+fn iter_familyt() {
+    struct S;
+    impl IterFamilyT for S {}
+
+    assert_eq!(S.exact().rev().len(), 0);
+    assert_eq!(S.fused().count(), 0);
+}
+
+// `async fn` and `impl Future`
+#[test_stubs]
+trait AsyncT {
+    async fn x(&self) -> u8;
+    fn y(&self) -> impl Future<Output = u8>;
+    fn z(&self) -> Option<impl Future<Output = u8>>;
+    fn w(&self) -> impl Future<Output = Vec<u8>> + Send;
+    fn unit(&self) -> impl Future<Output = ()>;
+    fn opt(&self) -> impl Future<Output = Option<Vec<u8>>>;
+}
+
+/// Poll `fut` to completion.
+fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = std::pin::pin!(fut);
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    loop {
+        if let std::task::Poll::Ready(x) = fut.as_mut().poll(&mut cx) {
+            return x;
+        }
+    }
+}
+
+#[test]
+fn asynct() {
+    struct S;
+    impl AsyncT for S {}
+
+    let msg = |f: fn() -> u8| {
+        let e = std::panic::catch_unwind(f).unwrap_err();
+        e.downcast_ref::<&str>().unwrap().to_string()
+    };
+    assert!(msg(|| block_on(S.x())).ends_with(": x"));
+    assert!(msg(|| block_on(S.y())).ends_with(": y"));
+    assert!(msg(|| block_on(S.z().unwrap())).ends_with(": z"));
+    assert!(block_on(S.w()).is_empty());
+    block_on(S.unit());
+    assert_eq!(block_on(S.opt()), Some(Vec::new()));
+}
+
+// Strings
+#[test_stubs]
+trait StringT {
+    fn name(&self) -> String;
+    fn tag(&self) -> &str;
+    fn label<'a>(&'a self) -> Option<&'a str>;
+    fn mut_str(&mut self) -> &mut str;
+    fn os(&self) -> std::ffi::OsString;
+    fn path(&self) -> Option<std::path::PathBuf>;
+    fn c(&self) -> std::ffi::CString;
+}
+
+#[test]
+fn stringt() {
+    struct S;
+    impl StringT for S {}
+
+    assert_eq!(S.name(), "");
+    assert_eq!(S.tag(), "");
+    assert_eq!(S.label(), Some(""));
+    assert!(std::panic::catch_unwind(|| S.mut_str().len()).is_err());
+    assert!(S.os().is_empty());
+    assert_eq!(S.path(), Some(std::path::PathBuf::new()));
+    assert!(S.c().is_empty());
+}
+
 // Tuples
 #[test_stubs]
 trait TupleT {
@@ -80,6 +310,22 @@ fn nested_runtime_call() {
     assert_eq!(s.x2(), (1, 2));
 }
 
+// Nested `Option`s
+#[test_stubs]
+trait NestedOptionT {
+    fn x(&self) -> Option<Option<u8>>;
+    fn y(&self) -> Option<Option<Vec<u8>>>;
+}
+
+#[test]
+fn nested_optiont() {
+    struct S;
+    impl NestedOptionT for S {}
+
+    assert!(std::panic::catch_unwind(|| S.x()).is_err());
+    assert_eq!(S.y(), Some(Some(Vec::new())));
+}
+
 // `self` types
 #[test_stubs]
 trait SelfT {
@@ -99,3 +345,1642 @@ fn selft() {
     let _ = std::panic::catch_unwind(|| S.x());
     assert_eq!(S.x2(), 3);
 }
+
+// Smart pointer receivers don't require `Self: Sized`, so the trait stays object safe.
+#[test_stubs]
+trait PtrSelfT {
+    fn rc(self: std::rc::Rc<Self>) -> u8;
+    fn arc(self: std::sync::Arc<Self>) -> Option<Vec<u8>>;
+    fn boxed(self: Box<Self>) -> u8;
+}
+
+#[test]
+fn ptr_selft() {
+    struct S;
+    impl PtrSelfT for S {}
+
+    let rc: std::rc::Rc<dyn PtrSelfT> = std::rc::Rc::new(S);
+    assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| rc.rc())).is_err());
+    let arc: std::sync::Arc<dyn PtrSelfT> = std::sync::Arc::new(S);
+    assert_eq!(arc.arc(), Some(Vec::new()));
+    let boxed: Box<dyn PtrSelfT> = Box::new(S);
+    assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| boxed.boxed())).is_err());
+}
+
+// `self` by value with a trait-level generic return type
+#[test_stubs]
+trait BuilderT<T> {
+    fn build(self) -> T;
+}
+
+#[test]
+fn buildert() {
+    struct S;
+    impl BuilderT<String> for S {}
+
+    // The stub gains `where Self: Sized`, and `todo!()` coerces to `T`.
+    assert!(std::panic::catch_unwind(|| S.build()).is_err());
+}
+
+// Generic return types bounded by `Default`
+#[test_stubs]
+trait DefaultGenT<U: Default> {
+    fn make<T: Default>(&self) -> T;
+    fn make_where<T>(&self) -> T
+    where
+        T: Default;
+    fn make_trait(&self) -> U;
+    fn make_from<T: From<u8>>(&self) -> T;
+}
+
+#[test]
+fn default_gent() {
+    struct S;
+    impl DefaultGenT<u16> for S {}
+
+    assert_eq!(S.make::<u8>(), 0);
+    assert_eq!(S.make_where::<String>(), "");
+    assert_eq!(S.make_trait(), 0);
+    // Only `Default` bounds are understood: other bounds fall back to `todo!()`.
+    assert!(std::panic::catch_unwind(|| S.make_from::<u32>()).is_err());
+}
+
+// `MaybeUninit`
+#[test_stubs]
+trait MaybeUninitT {
+    fn uninit(&self) -> std::mem::MaybeUninit<u64>;
+}
+
+#[test]
+fn maybe_uninitt() {
+    struct S;
+    impl MaybeUninitT for S {}
+
+    // The stub doesn't panic, but the value is uninitialized, so we must not read it.
+    let mut x = S.uninit();
+    x.write(3);
+    assert_eq!(unsafe { x.assume_init() }, 3);
+}
+
+// `unsafe fn`s are stubbed like any other method, and their stubs can only be called from `unsafe`
+// code.
+#[test_stubs]
+trait UnsafeT {
+    unsafe fn x(&self) -> u8;
+    unsafe fn y(&self) -> Vec<u8>;
+}
+
+#[test]
+fn unsafet() {
+    struct S;
+    impl UnsafeT for S {}
+
+    assert!(std::panic::catch_unwind(|| unsafe { S.x() }).is_err());
+    assert!(unsafe { S.y() }.is_empty());
+}
+
+// `impl Iterator` whose item is a trait-level generic
+#[test_stubs]
+trait GenIterT<U> {
+    fn iter(&self) -> impl Iterator<Item = U>;
+}
+
+#[test]
+fn gen_itert() {
+    struct S;
+    impl GenIterT<u8> for S {}
+
+    let _ = std::panic::catch_unwind(|| S.iter().count());
+}
+
+// `Weak`
+#[test_stubs]
+trait WeakT {
+    fn weak_ref(&self) -> std::sync::Weak<u32>;
+    fn rc_weak_ref(&self) -> std::rc::Weak<u32>;
+}
+
+#[test]
+fn weakt() {
+    struct S;
+    impl WeakT for S {}
+
+    assert!(S.weak_ref().upgrade().is_none());
+    assert!(S.rc_weak_ref().upgrade().is_none());
+}
+
+// An unqualified `Weak` may be either `std::rc::Weak` or `std::sync::Weak`.
+mod unqualified_weak {
+    use std::rc::Weak;
+    use test_stubs::test_stubs;
+
+    #[test_stubs]
+    trait UnqualifiedWeakT {
+        fn weak_ref(&self) -> Weak<u32>;
+    }
+
+    #[test]
+    fn unqualified_weakt() {
+        struct S;
+        impl UnqualifiedWeakT for S {}
+
+        assert!(S.weak_ref().upgrade().is_none());
+    }
+}
+
+// `once`
+#[test_stubs(once)]
+trait OnceT {
+    fn x(&self);
+    fn make<T: Default>(&self) -> T;
+}
+
+#[test]
+#[should_panic(expected = "make called more than once")]
+fn once() {
+    struct S;
+    impl OnceT for S {}
+
+    assert_eq!(S.make::<u8>(), 0);
+    S.make::<u8>();
+}
+
+#[test]
+#[should_panic(expected = "x called more than once")]
+fn once_panicking() {
+    struct S;
+    impl OnceT for S {}
+
+    let _ = std::panic::catch_unwind(|| S.x());
+    S.x();
+}
+
+// Types with lifetime arguments
+#[test_stubs]
+trait LifetimeArgsT {
+    fn entry<'a>(
+        &self,
+        m: &'a mut std::collections::HashMap<u8, u8>,
+    ) -> std::collections::hash_map::Entry<'a, u8, u8>;
+    fn args<'a>(&'a self) -> std::fmt::Arguments<'a>;
+}
+
+#[test]
+fn lifetime_argst() {
+    struct S;
+    impl LifetimeArgsT for S {}
+
+    let mut m = std::collections::HashMap::new();
+    assert!(
+        std::panic::catch_unwind(move || {
+            S.entry(&mut m);
+        })
+        .is_err()
+    );
+    assert!(
+        std::panic::catch_unwind(|| {
+            S.args();
+        })
+        .is_err()
+    );
+}
+
+// Collections
+#[test_stubs]
+trait CollectionsT {
+    fn queue(&self) -> std::collections::VecDeque<String>;
+    fn map(&self) -> std::collections::HashMap<String, Unstubbable>;
+    fn vec(&self) -> Vec<u8>;
+    fn counts(&self) -> std::collections::HashMap<String, u32>;
+    fn set(&self) -> std::collections::HashSet<u8>;
+    fn btree_map(&self) -> std::collections::BTreeMap<String, u32>;
+    fn btree_set(&self) -> std::collections::BTreeSet<u8>;
+    fn list(&self) -> std::collections::LinkedList<u8>;
+    fn random_state_map(
+        &self,
+    ) -> std::collections::HashMap<u8, u8, std::collections::hash_map::RandomState>;
+    fn custom_map(
+        &self,
+    ) -> std::collections::HashMap<
+        u8,
+        u8,
+        std::hash::BuildHasherDefault<std::collections::hash_map::DefaultHasher>,
+    >;
+    fn custom_set(
+        &self,
+    ) -> std::collections::HashSet<
+        u8,
+        std::hash::BuildHasherDefault<std::collections::hash_map::DefaultHasher>,
+    >;
+}
+
+// A type for which `test_stubs` has no stub.
+struct Unstubbable;
+
+#[test]
+fn collectionst() {
+    struct S;
+    impl CollectionsT for S {}
+
+    assert!(S.queue().is_empty());
+    // Collections are always empty, so their element types needn't be stubbable.
+    assert!(S.map().is_empty());
+    assert!(S.vec().is_empty());
+    assert!(S.counts().is_empty());
+    assert!(S.set().is_empty());
+    assert!(S.btree_map().is_empty());
+    assert!(S.btree_set().is_empty());
+    assert!(S.list().is_empty());
+    assert!(S.random_state_map().is_empty());
+    // Custom hashers only need to implement `Default`.
+    assert!(S.custom_map().is_empty());
+    assert!(S.custom_set().is_empty());
+}
+
+// `cfg`
+#[test_stubs(cfg = "not(any())")]
+trait CfgT {
+    fn x(&self) -> u8;
+}
+
+#[test_stubs(cfg = "any()")]
+trait CfgNeverT {
+    fn x(&self) -> u8;
+}
+
+#[test_stubs(cfg = all(test, not(any())), once)]
+trait CfgUnquotedT {
+    fn x(&self) -> u8;
+    // Already gated on the `cfg` predicate, so left unchanged.
+    #[cfg(all(test, not(any())))]
+    fn y(&self) -> u8;
+}
+
+#[test]
+fn cfgt() {
+    struct S;
+    impl CfgT for S {}
+    // `CfgNeverT`'s stubs are never generated, so `x` must be implemented.
+    impl CfgNeverT for S {
+        fn x(&self) -> u8 {
+            1
+        }
+    }
+
+    assert!(std::panic::catch_unwind(|| CfgT::x(&S)).is_err());
+    assert_eq!(CfgNeverT::x(&S), 1);
+}
+
+#[test]
+fn cfg_unquotedt() {
+    struct S;
+    impl CfgUnquotedT for S {
+        fn y(&self) -> u8 {
+            2
+        }
+    }
+
+    assert!(std::panic::catch_unwind(|| S.x()).is_err());
+    assert_eq!(S.y(), 2);
+}
+
+// `collections = "collect"`
+#[test_stubs(collections = "collect")]
+trait CollectT {
+    fn v(&self) -> Option<Vec<String>>;
+    fn m(&self) -> std::collections::HashMap<u8, Unstubbable, std::hash::RandomState>;
+    fn q(&self) -> std::collections::VecDeque<u8>;
+    fn s(&self) -> std::collections::BTreeSet<u8>;
+}
+
+#[test]
+fn collectt() {
+    struct S;
+    impl CollectT for S {}
+
+    assert!(S.v().unwrap().is_empty());
+    assert!(S.m().is_empty());
+    assert!(S.q().is_empty());
+    assert!(S.s().is_empty());
+}
+
+// Items which `syn` parses as `TraitItem::Verbatim` are passed through unchanged. Such items aren't
+// valid Rust, but they are accepted if they are `cfg`d out.
+#[test_stubs]
+trait VerbatimT {
+    #[cfg(any())]
+    pub fn vis(&self);
+    #[cfg(any())]
+    default fn dflt(&self);
+    fn x(&self) -> u8;
+}
+
+#[test]
+fn verbatimt() {
+    struct S;
+    impl VerbatimT for S {}
+
+    assert!(std::panic::catch_unwind(|| S.x()).is_err());
+}
+
+// `impl Error`
+#[test_stubs]
+trait ErrorT {
+    fn err(&self) -> impl std::error::Error;
+    fn err_bounds(&self) -> impl std::error::Error + Send + Sync + 'static;
+    fn opt_err(&self) -> Option<impl std::error::Error + Send + Sync + 'static>;
+}
+
+#[test]
+fn errort() {
+    struct S;
+    impl ErrorT for S {}
+
+    assert!(std::panic::catch_unwind(|| S.err().to_string()).is_err());
+    assert!(std::panic::catch_unwind(|| S.err_bounds().to_string()).is_err());
+    assert!(std::panic::catch_unwind(|| S.opt_err().map(|x| x.to_string())).is_err());
+}
+
+// `TEST_STUBS_MACRO`. Since the environment variable is read when `test_stubs` is expanded, we
+// build and run a separate crate (in `tests/env_macro`) with it set.
+#[test]
+fn env_macro() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+    let out = std::process::Command::new(env!("CARGO"))
+        .args(["run", "--quiet", "--offline"])
+        .current_dir(dir.join("tests").join("env_macro"))
+        .env("TEST_STUBS_MACRO", "unimplemented")
+        .env("CARGO_TARGET_DIR", dir.join("target").join("env_macro"))
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert_eq!(String::from_utf8(out.stdout).unwrap(), "not implemented: x");
+}
+
+// `macro`
+#[test_stubs(macro = unimplemented)]
+trait UnimplementedT {
+    fn x(&self);
+    fn y(&self) -> Option<u8>;
+}
+
+#[test_stubs(macro = todo)]
+trait TodoT {
+    fn x(&self);
+}
+
+#[test_stubs(macro = "unimplemented")]
+trait UnimplementedStrT {
+    fn z(&self) -> Box<Option<u8>>;
+}
+
+#[test]
+fn macrot() {
+    struct S;
+    impl UnimplementedT for S {}
+    impl TodoT for S {}
+    impl UnimplementedStrT for S {}
+
+    let msg = |f: fn()| {
+        let e = std::panic::catch_unwind(f).unwrap_err();
+        e.downcast_ref::<&str>().unwrap().to_string()
+    };
+    assert_eq!(msg(|| UnimplementedT::x(&S)), "not implemented: x");
+    assert_eq!(
+        msg(|| {
+            S.y();
+        }),
+        "not implemented: y"
+    );
+    assert_eq!(msg(|| TodoT::x(&S)), "not yet implemented: x");
+    assert_eq!(
+        msg(|| {
+            S.z();
+        }),
+        "not implemented: z"
+    );
+}
+
+// `Pin<Box<T>>`
+#[test_stubs]
+trait PinT {
+    fn pinned(&self) -> std::pin::Pin<Box<u32>>;
+    fn pinned_slice(&self) -> std::pin::Pin<Box<[u8]>>;
+    fn boxed_slice(&self) -> Box<[u8]>;
+    fn boxed_str(&self) -> Box<str>;
+}
+
+#[test]
+fn pint() {
+    struct S;
+    impl PinT for S {}
+
+    assert!(std::panic::catch_unwind(|| S.pinned()).is_err());
+    assert!(S.pinned_slice().is_empty());
+    assert!(S.boxed_slice().is_empty());
+    assert!(S.boxed_str().is_empty());
+}
+
+// Pinned futures and values
+#[test_stubs]
+trait PinFutureT {
+    fn fut(&self) -> std::pin::Pin<Box<dyn Future<Output = u8> + Send>>;
+    fn vec_fut(&self) -> std::pin::Pin<Box<dyn Future<Output = Vec<u8>>>>;
+    fn value(&self) -> std::pin::Pin<Box<Option<Vec<u8>>>>;
+    fn unit_fut<'a>(&'a self) -> std::pin::Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
+    fn opt_fut(&self) -> std::pin::Pin<Box<dyn Future<Output = Option<Vec<u8>>> + Send + 'static>>;
+}
+
+#[test]
+fn pin_futuret() {
+    struct S;
+    impl PinFutureT for S {}
+
+    assert!(std::panic::catch_unwind(|| block_on(S.fut())).is_err());
+    assert!(block_on(S.vec_fut()).is_empty());
+    assert_eq!(*S.value(), Some(Vec::new()));
+    block_on(S.unit_fut());
+    assert_eq!(block_on(S.opt_fut()), Some(Vec::new()));
+}
+
+// Boxed closures
+#[test_stubs]
+trait BoxedClosureT {
+    fn fn_mut(&self) -> Box<dyn FnMut(u8) -> String>;
+    fn fn_once(&self) -> Box<dyn FnOnce(u8) -> Vec<u8>>;
+    fn fn_unit(&self) -> Box<dyn Fn()>;
+}
+
+#[test]
+fn boxed_closuret() {
+    struct S;
+    impl BoxedClosureT for S {}
+
+    assert_eq!(S.fn_mut()(1), "");
+    assert!(S.fn_once()(1).is_empty());
+    S.fn_unit()();
+}
+
+// `impl Fn` closures
+#[test_stubs]
+trait ImplClosureT {
+    fn make(&self) -> impl Fn(u8) -> u8;
+    fn nullary(&self) -> impl Fn() -> Vec<u8>;
+    fn fn_mut(&self) -> impl FnMut(u8, &str) -> Option<String> + Send;
+    fn fn_once(&self) -> impl FnOnce();
+    fn two(&self) -> impl Fn(u8, u16) -> u32;
+    fn fn_mut_nullary(&self) -> impl FnMut() -> Vec<u8>;
+    fn fn_once_three(&self) -> impl FnOnce(String, Vec<u8>, bool) -> Option<Vec<u8>>;
+}
+
+#[test]
+fn impl_closuret() {
+    struct S;
+    impl ImplClosureT for S {}
+
+    let f = S.make();
+    assert!(std::panic::catch_unwind(|| f(1)).is_err());
+    assert!(S.nullary()().is_empty());
+    assert_eq!(S.fn_mut()(1, "a"), Some(String::new()));
+    S.fn_once()();
+    let f = S.two();
+    assert!(std::panic::catch_unwind(|| f(1, 2)).is_err());
+    assert!(S.fn_mut_nullary()().is_empty());
+    assert_eq!(
+        S.fn_once_three()(String::new(), vec![1], true),
+        Some(Vec::new())
+    );
+}
+
+// `Box<dyn Any>`
+#[test_stubs]
+trait AnyT {
+    fn any(&self) -> Box<dyn std::any::Any>;
+    fn any_send_sync(&self) -> std::boxed::Box<dyn std::any::Any + Send + Sync>;
+}
+
+#[test]
+fn anyt() {
+    struct S;
+    impl AnyT for S {}
+
+    assert!(S.any().is::<()>());
+    assert!(S.any_send_sync().is::<()>());
+}
+
+// `Box<dyn Trait>` and `Arc<dyn Trait>`
+#[test_stubs]
+trait DynT {
+    fn box_iter(&self) -> Box<dyn Iterator<Item = u8>>;
+    fn box_err(&self) -> Box<dyn std::error::Error + Send + Sync>;
+    fn arc_iter(&self) -> std::sync::Arc<dyn ExactSizeIterator<Item = u8>>;
+    fn arc_err(&self) -> std::sync::Arc<dyn std::error::Error>;
+    fn arc_any(&self) -> std::sync::Arc<dyn std::any::Any + Send + Sync>;
+    fn arc_fn(&self) -> std::sync::Arc<dyn Fn(u8) -> Vec<u8>>;
+    fn arc_local(&self) -> std::sync::Arc<dyn LocalTrait>;
+}
+
+// A trait for which `test_stubs` has no stub.
+trait LocalTrait {}
+
+#[test]
+fn dynt() {
+    struct S;
+    impl DynT for S {}
+
+    assert_eq!(S.box_iter().count(), 0);
+    assert!(S.box_err().is::<std::fmt::Error>());
+    assert_eq!(S.arc_iter().len(), 0);
+    assert!(S.arc_err().is::<std::fmt::Error>());
+    assert!(S.arc_any().is::<()>());
+    assert!(S.arc_fn()(1).is_empty());
+    // `Arc::new(todo!())` wouldn't compile, so the whole `Arc` is stubbed with `todo!()`.
+    assert!(std::panic::catch_unwind(|| drop(S.arc_local())).is_err());
+}
+
+// `Cow` of a type other than `str` or `[T]`
+#[test_stubs]
+trait CowT {
+    fn custom(&self) -> std::borrow::Cow<'_, Custom>;
+}
+
+#[derive(Clone)]
+struct Custom;
+
+#[test]
+fn cowt() {
+    struct S;
+    impl CowT for S {}
+
+    assert!(std::panic::catch_unwind(|| S.custom().into_owned()).is_err());
+}
+
+// `Cow<str>` and `Cow<[T]>`, including inside `Result`
+#[test_stubs]
+trait CowStrT {
+    fn name(&self) -> Result<std::borrow::Cow<'_, str>, MyErr>;
+    fn bytes(&self) -> std::borrow::Cow<'static, [u8]>;
+    fn static_name(&self) -> std::borrow::Cow<'static, str>;
+    fn named<'a>(&'a self) -> Option<std::borrow::Cow<'a, str>>;
+}
+
+#[test]
+fn cow_strt() {
+    struct S;
+    impl CowStrT for S {}
+
+    assert_eq!(S.name(), Ok(std::borrow::Cow::Borrowed("")));
+    assert!(S.bytes().is_empty());
+    assert_eq!(S.static_name(), "");
+    assert_eq!(S.named(), Some(std::borrow::Cow::Borrowed("")));
+}
+
+// Wildcard parameters don't cause warnings.
+#[deny(warnings)]
+#[test_stubs]
+trait WildcardT {
+    fn process(&self, _: u32, _: String) -> bool;
+}
+
+#[test]
+fn wildcardt() {
+    struct S;
+    impl WildcardT for S {}
+
+    assert!(std::panic::catch_unwind(|| S.process(1, String::new())).is_err());
+}
+
+// Tuples containing unit
+#[test_stubs]
+trait UnitTupleT {
+    fn x(&self) -> (u8, ());
+    fn units(&self) -> ((), ());
+}
+
+#[test]
+fn unit_tuplet() {
+    struct S;
+    impl UnitTupleT for S {}
+
+    // Unit elements are stubbed as `()`, so only `u8` panics.
+    assert!(std::panic::catch_unwind(|| S.x()).is_err());
+    assert_eq!(S.units(), ((), ()));
+}
+
+// `impl Trait`s with only auto traits (and lifetimes) aren't iterators, and `todo!()` satisfies
+// them.
+#[test_stubs]
+trait AutoTraitT {
+    fn opaque(&self) -> impl Send + Sync;
+    fn opaque_static(&self) -> impl Send + Sync + Unpin + 'static;
+}
+
+#[test]
+fn auto_traitt() {
+    struct S;
+    impl AutoTraitT for S {}
+
+    assert!(std::panic::catch_unwind(|| drop(S.opaque())).is_err());
+    assert!(std::panic::catch_unwind(|| drop(S.opaque_static())).is_err());
+}
+
+// `impl From<T>` and `impl Into<T>`
+#[test_stubs]
+trait ConvT {
+    fn from_string(&self) -> impl From<String>;
+    fn into_u64(&self) -> impl Into<u64>;
+}
+
+#[test]
+fn convt() {
+    struct S;
+    impl ConvT for S {}
+
+    assert!(
+        std::panic::catch_unwind(|| {
+            S.from_string();
+        })
+        .is_err()
+    );
+    assert!(std::panic::catch_unwind(|| -> u64 { S.into_u64().into() }).is_err());
+}
+
+// Trait implementations
+trait PlainT {
+    fn a(&self) -> u8;
+    fn b(&self) -> u8;
+    fn c(&self) -> Option<impl Iterator<Item = u8>>;
+}
+
+#[test]
+fn impl_plaint() {
+    struct S;
+    #[test_stubs]
+    impl PlainT for S {
+        fn a(&self) -> u8 {
+            1
+        }
+        fn b(&self) -> u8;
+        fn c(&self) -> Option<impl Iterator<Item = u8>>;
+    }
+
+    assert_eq!(S.a(), 1);
+    assert!(std::panic::catch_unwind(|| S.b()).is_err());
+    assert_eq!(S.c().map(|x| x.count()), Some(0));
+}
+
+// Pointers
+#[test_stubs]
+trait PtrT {
+    fn const_ptr(&self) -> *const u8;
+    fn mut_ptr(&self) -> *mut u8;
+    fn non_null(&self) -> std::ptr::NonNull<u8>;
+}
+
+#[test]
+fn ptrt() {
+    struct S;
+    impl PtrT for S {}
+
+    assert!(std::panic::catch_unwind(|| S.const_ptr()).is_err());
+    assert!(std::panic::catch_unwind(|| S.mut_ptr()).is_err());
+    assert!(std::panic::catch_unwind(|| S.non_null()).is_err());
+}
+
+// Marker types
+#[test_stubs]
+trait MarkerT {
+    fn pin_marker(&self) -> std::marker::PhantomPinned;
+}
+
+#[test]
+fn markert() {
+    struct S;
+    impl MarkerT for S {}
+
+    let _: std::marker::PhantomPinned = S.pin_marker();
+}
+
+// References to trait objects
+#[test_stubs]
+trait DynRefT {
+    fn iter(&self) -> &dyn Iterator<Item = u8>;
+}
+
+#[test]
+fn dyn_reft() {
+    struct S;
+    impl DynRefT for S {}
+
+    // `&dyn Iterator` isn't special cased: `todo!()` coerces to it.
+    assert!(std::panic::catch_unwind(|| S.iter().size_hint()).is_err());
+}
+
+// `debug_args`
+#[deny(warnings)]
+#[test_stubs(debug_args)]
+trait DebugArgsT {
+    fn x(&self, a: u8, b: &str, _: u16, (c, d): (u8, u8));
+    fn y(&self, a: u8) -> std::collections::VecDeque<u8>;
+    fn z(&self);
+}
+
+#[test]
+#[should_panic(expected = "x(a = 1, b = \"s\")")]
+fn debug_args() {
+    struct S;
+    impl DebugArgsT for S {}
+
+    // `y` doesn't panic, so doesn't use its arguments.
+    assert!(S.y(1).is_empty());
+    assert!(std::panic::catch_unwind(|| S.z()).is_err());
+    S.x(1, "s", 2, (3, 4));
+}
+
+// `debug_args` stubs which run after the method returns move the arguments they print.
+#[test_stubs(debug_args)]
+trait DebugArgsAsyncT {
+    fn fut(&self, a: u8) -> impl Future<Output = u8>;
+    fn pinned(&self, a: u8, b: String) -> std::pin::Pin<Box<dyn Future<Output = u8>>>;
+    fn closure(&self, a: u8) -> impl Fn(u8) -> u8;
+    fn boxed_closure(&self, a: u8) -> Box<dyn Fn(u8) -> u8>;
+}
+
+#[test]
+fn debug_args_async() {
+    struct S;
+    impl DebugArgsAsyncT for S {}
+
+    let msg = |f: Box<dyn FnOnce() -> u8>| {
+        let e = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).unwrap_err();
+        e.downcast_ref::<String>().unwrap().to_owned()
+    };
+    assert!(msg(Box::new(|| block_on(S.fut(1)))).ends_with("fut(a = 1)"));
+    assert!(
+        msg(Box::new(|| block_on(S.pinned(1, "s".to_owned()))))
+            .ends_with("pinned(a = 1, b = \"s\")")
+    );
+    let f = S.closure(1);
+    assert!(msg(Box::new(move || f(2))).ends_with("closure(a = 1)"));
+    let f = S.boxed_closure(1);
+    assert!(msg(Box::new(move || f(2))).ends_with("boxed_closure(a = 1)"));
+}
+
+// `Result` aliases with a single type parameter
+mod result_alias {
+    use test_stubs::test_stubs;
+
+    #[derive(Debug)]
+    pub struct MyError;
+    type Result<T> = std::result::Result<T, MyError>;
+
+    #[test_stubs]
+    pub trait ResultAliasT {
+        fn x(&self) -> Result<Option<impl Iterator<Item = u8>>>;
+    }
+}
+
+#[test]
+fn result_aliast() {
+    use result_alias::ResultAliasT;
+    struct S;
+    impl ResultAliasT for S {}
+
+    assert_eq!(S.x().map(|x| x.map(|y| y.count())).ok(), Some(Some(0)));
+}
+
+// `Result`s with unusual generic arguments
+mod unusual_result {
+    use test_stubs::test_stubs;
+
+    pub type Result<'a, T> = std::result::Result<T, &'a str>;
+
+    #[test_stubs]
+    pub trait UnusualResultT {
+        fn x(&self) -> std::result::Result<impl Iterator<Item = u8>, impl std::error::Error>;
+        fn y(&self) -> Result<'static, Vec<u8>>;
+    }
+}
+
+#[test]
+fn unusual_resultt() {
+    use unusual_result::UnusualResultT;
+    struct S;
+    impl UnusualResultT for S {}
+
+    assert_eq!(S.x().map(|x| x.count()).ok(), Some(0));
+    assert_eq!(S.y(), Ok(Vec::new()));
+}
+
+// `uninit`
+#[test_stubs(uninit)]
+trait UninitT {
+    fn x(&self) -> u8;
+    fn y(&self) -> Option<f64>;
+    fn z(&self) -> bool;
+}
+
+#[test]
+fn uninitt() {
+    struct S;
+    impl UninitT for S {}
+
+    // Calling `x` or `y` would be undefined behaviour, so we only check that their stubs compile.
+    let _: fn(&S) -> u8 = <S as UninitT>::x;
+    let _: fn(&S) -> Option<f64> = <S as UninitT>::y;
+    // Only numeric types are stubbed as uninitialized values.
+    assert!(std::panic::catch_unwind(|| S.z()).is_err());
+}
+
+// `Arc`
+#[test_stubs]
+trait ArcT {
+    fn arc_str(&self) -> std::sync::Arc<str>;
+    fn arc_slice(&self) -> std::sync::Arc<[u8]>;
+}
+
+#[test]
+fn arct() {
+    struct S;
+    impl ArcT for S {}
+
+    assert_eq!(&*S.arc_str(), "");
+    assert!(S.arc_slice().is_empty());
+}
+
+// `Arc<T>` and `Rc<T>`
+#[test_stubs]
+trait RcT {
+    fn arc(&self) -> std::sync::Arc<Vec<u8>>;
+    fn rc(&self) -> std::rc::Rc<u8>;
+    fn rc_str(&self) -> std::rc::Rc<str>;
+    fn rc_slice(&self) -> std::rc::Rc<[u8]>;
+    fn nested(&self) -> Option<std::sync::Arc<impl Iterator<Item = u8>>>;
+    fn rc_local(&self) -> std::rc::Rc<dyn LocalTrait>;
+    fn rc_local_send(&self) -> std::rc::Rc<dyn LocalTrait + Send>;
+}
+
+#[test]
+fn rct() {
+    struct S;
+    impl RcT for S {}
+
+    assert!(S.arc().is_empty());
+    assert!(std::panic::catch_unwind(|| S.rc()).is_err());
+    assert_eq!(&*S.rc_str(), "");
+    assert!(S.rc_slice().is_empty());
+    assert_eq!(S.nested().map(|x| x.size_hint()), Some((0, Some(0))));
+    assert!(std::panic::catch_unwind(|| drop(S.rc_local())).is_err());
+    assert!(std::panic::catch_unwind(|| drop(S.rc_local_send())).is_err());
+}
+
+// `warn_silent`
+mod warn_silent {
+    // Silence the warning `warn_silent` exists to emit.
+    #![allow(deprecated)]
+    use test_stubs::test_stubs;
+
+    #[test_stubs(warn_silent)]
+    pub trait WarnSilentT {
+        fn a(&self) -> u8;
+        fn b(&self) -> std::collections::VecDeque<u8>;
+        fn c<T: Default>(&self) -> Option<T>;
+        fn d(&self) -> Option<(u8, ())>;
+        fn e(&self);
+    }
+
+    #[test]
+    fn warn_silentt() {
+        struct S;
+        impl WarnSilentT for S {}
+
+        assert!(S.b().is_empty());
+        assert_eq!(WARN_SILENT_T_SILENT_STUBS, ["b", "c"]);
+    }
+}
+
+// `count_calls`
+#[test_stubs(count_calls)]
+trait CountCallsT {
+    fn x(&self) -> Vec<u8>;
+    fn y(&self) -> u8;
+}
+
+#[test]
+fn count_callst() {
+    use std::sync::atomic::Ordering;
+    struct S;
+    impl CountCallsT for S {}
+
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..10 {
+                    S.x();
+                }
+            });
+        }
+    });
+    assert_eq!(COUNT_CALLS_T_CALLS.load(Ordering::Relaxed), 40);
+    // Stubs which panic are counted too.
+    assert!(std::panic::catch_unwind(|| S.y()).is_err());
+    assert_eq!(COUNT_CALLS_T_CALLS.load(Ordering::Relaxed), 41);
+}
+
+// `cow_default`
+#[test_stubs(cow_default = "std::borrow::Cow::Owned(Default::default())")]
+trait CowDefaultT {
+    fn doc(&self) -> std::borrow::Cow<'_, Doc>;
+    fn name(&self) -> std::borrow::Cow<'static, str>;
+    fn bytes(&self) -> std::borrow::Cow<'_, [u8]>;
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Doc(u8);
+
+#[test]
+fn cow_defaultt() {
+    struct S;
+    impl CowDefaultT for S {}
+
+    assert_eq!(S.doc().into_owned(), Doc(0));
+    // `Cow<str>` and `Cow<[T]>` are still borrowed.
+    assert!(matches!(S.name(), std::borrow::Cow::Borrowed("")));
+    assert!(matches!(S.bytes(), std::borrow::Cow::Borrowed([])));
+}
+
+// `impl Iterator` whose item is a tuple
+#[test_stubs]
+trait MapIterT<K, V> {
+    fn entries(&self) -> impl Iterator<Item = (K, V)>;
+    fn into_entries(&self) -> impl IntoIterator<Item = (K, V)>;
+    fn map(&self) -> std::collections::HashMap<K, V>;
+}
+
+#[test]
+fn map_itert() {
+    struct S;
+    impl MapIterT<String, u32> for S {}
+
+    assert_eq!(S.entries().count(), 0);
+    assert_eq!(S.into_entries().into_iter().count(), 0);
+    assert!(S.map().is_empty());
+}
+
+// `test_only`
+#[test_stubs(test_only)]
+trait TestOnlyT {
+    fn x(&self) -> u8;
+    fn y(self) -> Option<u8>;
+}
+
+#[test]
+fn test_onlyt() {
+    struct S;
+    impl TestOnlyT for S {}
+
+    assert!(std::panic::catch_unwind(|| S.x()).is_err());
+    assert!(std::panic::catch_unwind(|| S.y()).is_err());
+}
+
+// Standard library types which implement `Default`
+#[test_stubs]
+trait StdDefaultT {
+    fn hasher_state(&self) -> std::collections::hash_map::RandomState;
+    fn hasher(&self) -> std::hash::BuildHasherDefault<std::collections::hash_map::DefaultHasher>;
+    fn duration(&self) -> std::time::Duration;
+    fn phantom(&self) -> std::marker::PhantomData<String>;
+}
+
+#[test]
+fn std_defaultt() {
+    use std::hash::BuildHasher;
+    struct S;
+    impl StdDefaultT for S {}
+
+    S.hasher_state().hash_one(1);
+    S.hasher().hash_one(1);
+    assert_eq!(S.duration(), std::time::Duration::ZERO);
+    let _: std::marker::PhantomData<String> = S.phantom();
+}
+
+// `NonZero`
+#[test_stubs]
+trait NonZeroT {
+    fn id(&self) -> std::num::NonZero<u32>;
+    fn alias(&self) -> Option<std::num::NonZeroI64>;
+}
+
+#[test]
+fn non_zerot() {
+    struct S;
+    impl NonZeroT for S {}
+
+    assert_eq!(S.id().get(), 1);
+    assert_eq!(S.alias().unwrap().get(), 1);
+}
+
+// `impl Trait` nested in a more complex type
+#[test_stubs]
+trait NestedImplT {
+    fn x(&self) -> Result<Option<(u8, impl Iterator<Item = u8>, Box<u8>)>, ()>;
+}
+
+#[test]
+fn nested_implt() {
+    struct S;
+    impl NestedImplT for S {}
+
+    // The `Empty` cast is applied only to the `impl Iterator` element, so this compiles.
+    assert!(std::panic::catch_unwind(|| S.x().map(|x| x.map(|(_, y, _)| y.count()))).is_err());
+}
+
+// The canonical I/O read shape
+#[test_stubs]
+trait ReadT {
+    fn read_all(&mut self) -> Result<Vec<u8>, std::io::Error>;
+}
+
+#[test]
+fn readt() {
+    struct S;
+    impl ReadT for S {}
+
+    assert!(S.read_all().unwrap().is_empty());
+}
+
+// `seed`
+#[test_stubs(seed = 42)]
+trait SeedT {
+    fn a(&self) -> u32;
+    fn b(&self) -> u32;
+    fn c(&self) -> Option<(i8, bool, f64, u128)>;
+    fn d(&self) -> char;
+}
+
+#[test]
+fn seedt() {
+    struct S;
+    impl SeedT for S {}
+
+    assert_ne!(S.a(), S.b());
+    assert_eq!(S.a(), S.a());
+    let (w, _, y, _) = S.c().unwrap();
+    assert!(w >= 0);
+    assert!((0.0..1000.0).contains(&y));
+    // Non-primitive types are unaffected.
+    assert!(std::panic::catch_unwind(|| S.d()).is_err());
+}
+
+// `enum_default`
+#[test_stubs(enum_default(Mode = "Mode::Off", Level = "Level::Low"))]
+trait EnumDefaultT {
+    fn mode(&self) -> Mode;
+    fn levels(&self) -> Option<(Level, self::Mode)>;
+}
+
+#[derive(Debug, PartialEq)]
+enum Mode {
+    Off,
+    #[allow(dead_code)]
+    On,
+}
+
+#[derive(Debug, PartialEq)]
+enum Level {
+    Low,
+}
+
+#[test]
+fn enum_defaultt() {
+    struct S;
+    impl EnumDefaultT for S {}
+
+    assert_eq!(S.mode(), Mode::Off);
+    assert_eq!(S.levels(), Some((Level::Low, Mode::Off)));
+}
+
+// `impl_for`
+#[test_stubs(impl_for = "ImplForS")]
+trait ImplForT<U: Default> {
+    fn x(&self) -> Option<U>;
+    fn y(&self) -> Vec<U>;
+    fn z(&self) -> u8;
+}
+
+struct ImplForS;
+
+#[test]
+fn impl_fort() {
+    assert_eq!(ImplForT::<u8>::x(&ImplForS), Some(0));
+    assert!(ImplForT::<String>::y(&ImplForS).is_empty());
+    assert!(std::panic::catch_unwind(|| ImplForT::<u8>::z(&ImplForS)).is_err());
+}
+
+// `vec_capacity`
+#[test_stubs(vec_capacity = 16, collections = "collect")]
+trait VecCapacityT {
+    fn x(&self) -> Vec<u8>;
+    fn y(&self) -> Option<Vec<String>>;
+    fn z(&self) -> std::collections::VecDeque<u8>;
+}
+
+#[test]
+fn vec_capacityt() {
+    struct S;
+    impl VecCapacityT for S {}
+
+    let x = S.x();
+    assert!(x.is_empty());
+    assert!(x.capacity() >= 16);
+    assert!(S.y().unwrap().capacity() >= 16);
+    assert!(S.z().is_empty());
+}
+
+// `stub_struct`
+#[test_stubs::stub_struct(MyStub)]
+#[test_stubs]
+trait StubStructT {
+    fn x(&self) -> u8;
+    fn y(&self) -> Vec<u8>;
+}
+
+#[test_stubs]
+#[test_stubs::stub_struct(MyGenStub)]
+trait StubStructGenT<'a, U: Default> {
+    fn x(&self) -> Option<U>;
+    fn y(&self) -> &'a str;
+}
+
+#[test]
+#[should_panic(expected = "not yet implemented: x")]
+fn stub_structt() {
+    assert!(MyStub.y().is_empty());
+    let s = MyGenStub::<'_, u8>(std::marker::PhantomData);
+    assert_eq!(s.x(), Some(0));
+    assert_eq!(s.y(), "");
+    MyStub.x();
+}
+
+#[test_stubs(cfg = "not(any())")]
+#[test_stubs::stub_struct(MyCfgStub, cfg = "not(any())")]
+trait StubStructCfgT {
+    fn x(&self) -> Vec<u8>;
+}
+
+#[test_stubs(cfg = not(any()))]
+#[test_stubs::stub_struct(MyCfgStub2, cfg = not(any()))]
+trait StubStructCfg2T {
+    fn x(&self) -> Vec<u8>;
+}
+
+#[test]
+fn stub_struct_cfgt() {
+    assert!(StubStructCfgT::x(&MyCfgStub).is_empty());
+    assert!(StubStructCfg2T::x(&MyCfgStub2).is_empty());
+}
+
+// `echo`
+#[test_stubs(echo)]
+trait EchoT {
+    fn timeout(&self, n: u8, d: std::time::Duration) -> std::time::Duration;
+    fn name<'a>(&self, s: &'a str) -> &'a str;
+    fn other(&self, _: u8, x: u16) -> u8;
+    fn elided(&self, s: &str) -> &str;
+    fn elided_cow(&self, s: std::borrow::Cow<'_, str>) -> std::borrow::Cow<'_, str>;
+    #[allow(mismatched_lifetime_syntaxes)]
+    fn hidden_cow(&self, s: std::borrow::Cow<str>) -> std::borrow::Cow<str>;
+}
+
+#[test]
+fn echot() {
+    struct S;
+    impl EchoT for S {}
+
+    let d = std::time::Duration::from_secs(3);
+    assert_eq!(S.timeout(1, d), d);
+    assert_eq!(S.name("a"), "a");
+    // Wildcard arguments can't be echoed.
+    assert!(std::panic::catch_unwind(|| S.other(1, 2)).is_err());
+    // Elided lifetimes are tied to `&self`, so the arguments can't be echoed.
+    assert_eq!(S.elided("a"), "");
+    assert_eq!(S.elided_cow("a".into()), "");
+    assert_eq!(S.hidden_cow("a".into()), "");
+}
+
+// `Result` with an uninhabited error type
+#[test_stubs]
+trait InfallibleT {
+    fn x(&self) -> Result<Vec<u8>, std::convert::Infallible>;
+}
+
+#[test]
+fn infallible() {
+    struct S;
+    impl InfallibleT for S {}
+
+    assert_eq!(S.x(), Ok(Vec::new()));
+}
+
+// `result = "err"`
+#[test_stubs(result = "err")]
+trait ResultErrT {
+    fn x(&self) -> Result<u8, MyErr>;
+    fn y(&self) -> Option<Result<impl Iterator<Item = u8>, std::fmt::Error>>;
+    fn z(&self) -> std::fmt::Error;
+    fn w(&self) -> Result<u8, impl std::error::Error>;
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct MyErr;
+
+#[test]
+fn result_errt() {
+    struct S;
+    impl ResultErrT for S {}
+
+    assert_eq!(S.x(), Err(MyErr));
+    assert!(matches!(S.y(), Some(Err(std::fmt::Error))));
+    assert_eq!(S.z(), std::fmt::Error);
+    assert!(std::panic::catch_unwind(|| S.w()).is_err());
+}
+
+// `blanket`
+trait BlanketA {
+    fn a(&self) -> u8 {
+        1
+    }
+}
+trait BlanketB<T> {
+    fn b(&self) -> Option<T> {
+        None
+    }
+}
+
+#[test_stubs(blanket)]
+trait BlanketT<T>: BlanketA + BlanketB<T> {}
+
+#[test]
+fn blankett() {
+    fn f<T, X: BlanketT<T>>(x: &X) -> (u8, Option<T>) {
+        (x.a(), x.b())
+    }
+
+    struct S;
+    impl BlanketA for S {}
+    impl BlanketB<u16> for S {}
+
+    assert_eq!(f(&S), (1, None));
+}
+
+// References to slices
+#[test_stubs]
+trait SliceRefT {
+    fn x(&self) -> &'static [&'static str];
+    fn y(&self) -> &[u8];
+    fn z(&self) -> Option<&[Vec<u8>]>;
+}
+
+#[test]
+fn slice_reft() {
+    struct S;
+    impl SliceRefT for S {}
+
+    assert!(S.x().is_empty());
+    assert!(S.y().is_empty());
+    assert_eq!(S.z(), Some(&[][..]));
+}
+
+// `slice_default`
+#[test_stubs(slice_default = "&[1, 2, 3]")]
+trait SliceDefaultT {
+    fn x(&self) -> &[u8];
+    fn y<'a>(&'a self) -> Option<&'a [u8]>;
+}
+
+#[test]
+fn slice_defaultt() {
+    struct S;
+    impl SliceDefaultT for S {}
+
+    assert_eq!(S.x(), &[1, 2, 3]);
+    assert_eq!(S.y(), Some(&[1, 2, 3][..]));
+}
+
+// `impl Iterator` whose item is concrete, or is itself an `impl Iterator`
+#[test_stubs]
+trait NestedIterT {
+    fn flat(&self) -> impl Iterator<Item = Option<u8>>;
+    fn strs(&self) -> impl DoubleEndedIterator<Item = &str>;
+    fn nested(&self) -> impl Iterator<Item = impl Iterator<Item = u8>>;
+}
+
+#[test]
+fn nested_itert() {
+    struct S;
+    impl NestedIterT for S {}
+
+    assert_eq!(S.flat().count(), 0);
+    assert_eq!(S.strs().next_back(), None);
+    // A nested `impl Iterator` item can't be named, so that stub still panics.
+    assert!(std::panic::catch_unwind(|| S.nested().flatten().count()).is_err());
+}
+
+// `impl Iterator` with a complex `Item` type
+#[test_stubs]
+trait ResultIterT {
+    fn lines(&self) -> impl Iterator<Item = std::io::Result<String>>;
+}
+
+#[test]
+fn result_itert() {
+    struct S;
+    impl ResultIterT for S {}
+
+    assert_eq!(S.lines().count(), 0);
+}
+
+// `black_box`
+#[test_stubs(black_box, seed = 3)]
+trait BlackBoxT {
+    fn x(&self) -> Vec<u8>;
+    fn y(&self) -> (u32, Option<bool>);
+    fn z(&self) -> &[u8];
+    fn w(&self) -> char;
+}
+
+#[test]
+fn black_boxt() {
+    struct S;
+    impl BlackBoxT for S {}
+
+    assert_eq!(S.x(), Vec::<u8>::new());
+    assert_eq!(S.y(), S.y());
+    assert!(S.z().is_empty());
+    assert!(std::panic::catch_unwind(|| S.w()).is_err());
+}
+
+// Stubs use fully qualified paths, so they work even if prelude names are shadowed and no `use`s
+// are in scope.
+#[allow(dead_code, non_snake_case)]
+mod qualified_paths {
+    use test_stubs::test_stubs;
+
+    struct Box;
+    struct Vec;
+    trait Default {}
+    fn Some() {}
+    fn Ok() {}
+    fn Err() {}
+
+    #[test_stubs]
+    trait QualifiedT {
+        fn a(&self) -> std::boxed::Box<u8>;
+        fn b(&self) -> std::option::Option<std::vec::Vec<u8>>;
+        fn c(&self) -> std::result::Result<std::time::Duration, ()>;
+        fn d(&self) -> std::pin::Pin<std::boxed::Box<[u8]>>;
+        fn e(&self) -> std::cell::OnceCell<u8>;
+        fn f(&self) -> std::sync::OnceLock<u8>;
+        fn g(&self) -> std::collections::VecDeque<u8>;
+        fn i(&self) -> core::option::Option<core::result::Result<std::time::Duration, ()>>;
+        fn j(&self) -> ::std::boxed::Box<[u8]>;
+    }
+
+    #[test_stubs(result = "err")]
+    trait QualifiedErrT {
+        fn h(&self) -> std::result::Result<u8, std::fmt::Error>;
+    }
+
+    #[test]
+    fn qualifiedt() {
+        struct S;
+        impl QualifiedT for S {}
+        impl QualifiedErrT for S {}
+
+        assert!(std::panic::catch_unwind(|| *S.a()).is_err());
+        assert_eq!(S.b(), std::option::Option::Some(std::vec::Vec::new()));
+        assert!(S.c().is_ok());
+        assert!(S.d().is_empty());
+        assert!(S.e().get().is_none());
+        assert!(S.f().get().is_none());
+        assert!(S.g().is_empty());
+        assert_eq!(
+            S.i(),
+            std::option::Option::Some(std::result::Result::Ok(std::time::Duration::ZERO))
+        );
+        assert!(S.j().is_empty());
+        assert!(S.h().is_err());
+    }
+}
+
+// Stubs don't rely on `std` resolving to the standard library.
+mod shadowed_std {
+    use test_stubs::test_stubs;
+
+    mod std {}
+
+    #[test_stubs(count_calls, once)]
+    trait ShadowedStdT {
+        fn a(&self) -> Option<()>;
+        fn b(&self) -> Result<(), ()>;
+        fn c(&self) -> core::ops::RangeInclusive<u8>;
+        fn d(&self) -> u8;
+    }
+
+    #[test]
+    fn shadowed_stdt() {
+        struct S;
+        impl ShadowedStdT for S {}
+
+        assert!(S.a().is_some());
+        assert!(S.b().is_ok());
+        assert!(S.c().is_empty());
+        assert!(::std::panic::catch_unwind(|| S.d()).is_err());
+    }
+}
+
+// Collection stubs compile without any collection imports, even if the macros stubs use are
+// shadowed.
+#[allow(unused_macros)]
+mod no_imports {
+    use test_stubs::test_stubs;
+
+    macro_rules! todo {
+        ($($x:tt)*) => {
+            0
+        };
+    }
+
+    #[test_stubs(once)]
+    trait NoImportsT {
+        fn x(&self) -> Option<std::collections::VecDeque<Vec<u8>>>;
+        fn y(&self) -> char;
+    }
+
+    #[test]
+    fn no_importst() {
+        struct S;
+        impl NoImportsT for S {}
+
+        assert!(S.x().unwrap().is_empty());
+        assert!(std::panic::catch_unwind(|| S.y()).is_err());
+    }
+}
+
+// `RangeInclusive`
+#[test_stubs]
+trait RangeInclusiveT {
+    fn span(&self) -> std::ops::RangeInclusive<usize>;
+    fn x(&self) -> Option<std::ops::RangeInclusive<i8>>;
+    fn y(&self) -> std::ops::RangeInclusive<char>;
+}
+
+#[test]
+fn range_inclusivet() {
+    struct S;
+    impl RangeInclusiveT for S {}
+
+    assert_eq!(S.span().count(), 0);
+    assert!(S.x().unwrap().is_empty());
+    assert!(std::panic::catch_unwind(|| S.y()).is_err());
+}
+
+// Defaulted generic parameters are preserved.
+#[test_stubs]
+trait GenDefaultT<U = u8> {
+    fn x(&self) -> Option<U>;
+}
+
+#[test]
+fn gen_defaultt() {
+    struct S;
+    impl GenDefaultT for S {}
+    impl GenDefaultT<String> for S {}
+
+    // `GenDefaultT` without arguments is `GenDefaultT<u8>`.
+    let f: fn(&S) -> Option<u8> = <S as GenDefaultT>::x;
+    assert!(std::panic::catch_unwind(|| f(&S)).is_err());
+    assert!(std::panic::catch_unwind(|| GenDefaultT::<String>::x(&S)).is_err());
+}
+
+// `default`
+#[derive(Debug, Default, PartialEq)]
+struct Settings {
+    verbose: bool,
+}
+
+#[test_stubs]
+trait NoDefaultT {
+    fn settings(&self) -> Settings;
+}
+
+#[test_stubs(default)]
+trait DefaultT<U> {
+    type A;
+    fn settings(&self) -> Settings;
+    fn opt(&self) -> Option<(u8, Settings)>;
+    fn v(&self) -> Vec<Settings>;
+    fn u(&self) -> U;
+    fn a(&self) -> Self::A;
+}
+
+#[test]
+fn defaultt() {
+    struct S;
+    impl NoDefaultT for S {}
+    impl DefaultT<u8> for S {
+        type A = u8;
+    }
+
+    assert!(std::panic::catch_unwind(|| NoDefaultT::settings(&S)).is_err());
+    assert_eq!(DefaultT::settings(&S), Settings::default());
+    assert_eq!(S.opt(), Some((0, Settings { verbose: false })));
+    assert!(S.v().is_empty());
+    assert!(std::panic::catch_unwind(|| S.u()).is_err());
+    assert!(std::panic::catch_unwind(|| S.a()).is_err());
+}
+
+// `stubbed_methods`
+#[test_stubs(stubbed_methods)]
+trait StubbedMethodsT {
+    fn x(&self) -> u8;
+    #[no_stub]
+    fn y(&self) -> u8;
+    fn z(&self) -> Vec<u8>;
+    fn w(&self) -> u8 {
+        0
+    }
+}
+
+#[test]
+fn stubbed_methodst() {
+    struct S;
+    impl StubbedMethodsT for S {
+        fn y(&self) -> u8 {
+            1
+        }
+    }
+
+    assert_eq!(S.y(), 1);
+    assert!(S.z().is_empty());
+    assert_eq!(STUBBED_METHODS_T_STUBBED_METHODS, &["x", "z"]);
+}
+
+// `strict` only rejects `impl Trait`s which can't be stubbed.
+#[test_stubs(strict)]
+trait StrictT {
+    fn iter(&self) -> impl Iterator<Item = u8>;
+    fn fut(&self) -> impl Future<Output = Vec<u8>>;
+    fn f(&self) -> impl Fn(u8) -> u8;
+    fn auto(&self) -> impl Send + Sync + 'static;
+    fn x(&self) -> u8;
+}
+
+#[test]
+fn strictt() {
+    struct S;
+    impl StrictT for S {}
+
+    assert_eq!(S.iter().count(), 0);
+    assert!(block_on(S.fut()).is_empty());
+    assert!(std::panic::catch_unwind(|| drop(S.auto())).is_err());
+    assert!(std::panic::catch_unwind(|| S.f()(1)).is_err());
+    assert!(std::panic::catch_unwind(|| S.x()).is_err());
+}
+
+// `#[test_stub]` methods in inherent `impl` blocks
+struct Expensive;
+
+#[test_stubs]
+impl Expensive {
+    fn cheap(&self) -> u8 {
+        1
+    }
+
+    #[test_stub]
+    fn expensive(&self) -> u8 {
+        2
+    }
+
+    #[test_stub]
+    fn list(&self) -> Vec<u8> {
+        vec![3]
+    }
+
+    #[test_stub(expr = 4)]
+    fn other(&self) -> u8 {
+        5
+    }
+}
+
+#[test]
+fn inherent_impl() {
+    assert_eq!(Expensive.cheap(), 1);
+    assert!(std::panic::catch_unwind(|| Expensive.expensive()).is_err());
+    assert!(Expensive.list().is_empty());
+    assert_eq!(Expensive.other(), 4);
+}